@@ -0,0 +1,431 @@
+use crate::cpu::CpuFlag;
+use crate::instructions::LogicOperator;
+use crate::register_file::RegisterFile;
+use crate::registers::Register;
+
+fn mask(wide: bool) -> u32 {
+    if wide {
+        0xffff
+    } else {
+        0xff
+    }
+}
+
+fn sign_bit(wide: bool) -> u16 {
+    if wide {
+        0x8000
+    } else {
+        0x80
+    }
+}
+
+/// Sets ZF, SF and PF from a result, PF only ever looks at the low byte
+fn set_result_flags(flags: &mut RegisterFile, result: u16, wide: bool) {
+    flags.set_flag(CpuFlag::ZF, result == 0);
+    flags.set_flag(CpuFlag::SF, result & sign_bit(wide) != 0);
+    flags.set_flag(CpuFlag::PF, (result as u8).count_ones().is_multiple_of(2));
+}
+
+fn add_with_carry(flags: &mut RegisterFile, a: u16, b: u16, carry: bool, wide: bool) -> u16 {
+    let sum = a as u32 + b as u32 + carry as u32;
+    let result = (sum & mask(wide)) as u16;
+    flags.set_flag(CpuFlag::CF, sum > mask(wide));
+    flags.set_flag(CpuFlag::AF, (a ^ b ^ result) & 0x10 != 0);
+    flags.set_flag(
+        CpuFlag::OF,
+        (a ^ result) & (b ^ result) & sign_bit(wide) != 0,
+    );
+    set_result_flags(flags, result, wide);
+    result
+}
+
+fn sub_with_borrow(flags: &mut RegisterFile, a: u16, b: u16, borrow: bool, wide: bool) -> u16 {
+    let subtrahend = b as u32 + borrow as u32;
+    let result = ((a as u32).wrapping_sub(subtrahend) & mask(wide)) as u16;
+    flags.set_flag(CpuFlag::CF, subtrahend > a as u32);
+    flags.set_flag(CpuFlag::AF, (a ^ b ^ result) & 0x10 != 0);
+    flags.set_flag(CpuFlag::OF, (a ^ b) & (a ^ result) & sign_bit(wide) != 0);
+    set_result_flags(flags, result, wide);
+    result
+}
+
+/// AND, OR, XOR and TEST always clear CF and OF
+fn logic(flags: &mut RegisterFile, result: u16, wide: bool) -> u16 {
+    flags.set_flag(CpuFlag::CF, false);
+    flags.set_flag(CpuFlag::OF, false);
+    flags.set_flag(CpuFlag::AF, false);
+    set_result_flags(flags, result, wide);
+    result
+}
+
+pub fn add(flags: &mut RegisterFile, a: u16, b: u16, wide: bool) -> u16 {
+    add_with_carry(flags, a, b, false, wide)
+}
+
+pub fn adc(flags: &mut RegisterFile, a: u16, b: u16, wide: bool) -> u16 {
+    let carry = flags.flag(CpuFlag::CF);
+    add_with_carry(flags, a, b, carry, wide)
+}
+
+/// Also used for CMP, DEC and NEG
+pub fn sub(flags: &mut RegisterFile, a: u16, b: u16, wide: bool) -> u16 {
+    sub_with_borrow(flags, a, b, false, wide)
+}
+
+pub fn sbb(flags: &mut RegisterFile, a: u16, b: u16, wide: bool) -> u16 {
+    let borrow = flags.flag(CpuFlag::CF);
+    sub_with_borrow(flags, a, b, borrow, wide)
+}
+
+/// Also used for TEST
+pub fn and(flags: &mut RegisterFile, a: u16, b: u16, wide: bool) -> u16 {
+    logic(flags, a & b, wide)
+}
+
+pub fn or(flags: &mut RegisterFile, a: u16, b: u16, wide: bool) -> u16 {
+    logic(flags, a | b, wide)
+}
+
+pub fn xor(flags: &mut RegisterFile, a: u16, b: u16, wide: bool) -> u16 {
+    logic(flags, a ^ b, wide)
+}
+
+/// Shifts or rotates `value` one bit at a time, `count` times, the way the 8086 microcode does it,
+/// so CF and OF end up describing the last single bit step. Shifts also set SF, ZF and PF while
+/// rotates only touch CF and OF. A count of zero leaves the flags alone
+pub fn shift(
+    flags: &mut RegisterFile,
+    operator: LogicOperator,
+    value: u16,
+    count: u8,
+    wide: bool,
+) -> u16 {
+    if count == 0 {
+        return value;
+    }
+    let top = sign_bit(wide);
+    let mut result = value;
+    for _ in 0..count {
+        let carry = flags.flag(CpuFlag::CF);
+        let (next, carry_out) = match operator {
+            LogicOperator::SHL => (result << 1, result & top != 0),
+            LogicOperator::SHR => (result >> 1, result & 1 != 0),
+            LogicOperator::SAR => ((result >> 1) | (result & top), result & 1 != 0),
+            LogicOperator::ROL => {
+                let out = result & top != 0;
+                ((result << 1) | out as u16, out)
+            }
+            LogicOperator::ROR => {
+                let out = result & 1 != 0;
+                ((result >> 1) | if out { top } else { 0 }, out)
+            }
+            LogicOperator::RCL => ((result << 1) | carry as u16, result & top != 0),
+            LogicOperator::RCR => ((result >> 1) | if carry { top } else { 0 }, result & 1 != 0),
+        };
+        let next = (next as u32 & mask(wide)) as u16;
+        let overflow = match operator {
+            LogicOperator::SHL | LogicOperator::ROL | LogicOperator::RCL => {
+                (next & top != 0) != carry_out
+            }
+            LogicOperator::SHR => result & top != 0,
+            LogicOperator::SAR => false,
+            LogicOperator::ROR | LogicOperator::RCR => (next ^ (next << 1)) & top != 0,
+        };
+        flags.set_flag(CpuFlag::CF, carry_out);
+        flags.set_flag(CpuFlag::OF, overflow);
+        result = next;
+    }
+    if matches!(
+        operator,
+        LogicOperator::SHL | LogicOperator::SHR | LogicOperator::SAR
+    ) {
+        set_result_flags(flags, result, wide);
+    }
+    result
+}
+
+/// Decimal adjust AL after an addition. OF is left by the 8086 as the overflow of adding the
+/// correction to AL
+pub fn daa(registers: &mut RegisterFile) {
+    let al = registers.get(Register::AL);
+    let carry = registers.flag(CpuFlag::CF);
+    let mut correction = 0;
+    if al & 0x0f > 9 || registers.flag(CpuFlag::AF) {
+        correction |= 0x06;
+    }
+    if al > 0x99 || carry {
+        correction |= 0x60;
+    }
+    let result = add(registers, al, correction, false);
+    registers.set_flag(CpuFlag::AF, correction & 0x06 != 0);
+    registers.set_flag(CpuFlag::CF, correction & 0x60 != 0);
+    registers.set(Register::AL, result);
+}
+
+/// Decimal adjust AL after a subtraction, the mirror image of DAA
+pub fn das(registers: &mut RegisterFile) {
+    let al = registers.get(Register::AL);
+    let carry = registers.flag(CpuFlag::CF);
+    let mut correction = 0;
+    if al & 0x0f > 9 || registers.flag(CpuFlag::AF) {
+        correction |= 0x06;
+    }
+    if al > 0x99 || carry {
+        correction |= 0x60;
+    }
+    let result = sub(registers, al, correction, false);
+    registers.set_flag(CpuFlag::AF, correction & 0x06 != 0);
+    registers.set_flag(CpuFlag::CF, correction & 0x60 != 0);
+    registers.set(Register::AL, result);
+}
+
+/// ASCII adjust after an addition. The 8086 adds 6 to AL and 1 to AH separately, SF, ZF, PF and
+/// OF come from adding the correction to AL before the high nibble is cleared
+pub fn aaa(registers: &mut RegisterFile) {
+    let al = registers.get(Register::AL);
+    let adjust = al & 0x0f > 9 || registers.flag(CpuFlag::AF);
+    let correction = if adjust { 6 } else { 0 };
+    let result = add(registers, al, correction, false);
+    if adjust {
+        let ah = registers.get(Register::AH);
+        registers.set(Register::AH, ah.wrapping_add(1));
+    }
+    registers.set_flag(CpuFlag::AF, adjust);
+    registers.set_flag(CpuFlag::CF, adjust);
+    registers.set(Register::AL, result & 0x0f);
+}
+
+/// ASCII adjust after a subtraction, the mirror image of AAA
+pub fn aas(registers: &mut RegisterFile) {
+    let al = registers.get(Register::AL);
+    let adjust = al & 0x0f > 9 || registers.flag(CpuFlag::AF);
+    let correction = if adjust { 6 } else { 0 };
+    let result = sub(registers, al, correction, false);
+    if adjust {
+        let ah = registers.get(Register::AH);
+        registers.set(Register::AH, ah.wrapping_sub(1));
+    }
+    registers.set_flag(CpuFlag::AF, adjust);
+    registers.set_flag(CpuFlag::CF, adjust);
+    registers.set(Register::AL, result & 0x0f);
+}
+
+/// Splits AL into base `base` digits in AH and AL, `base` can't be zero. SF, ZF and PF describe
+/// the new AL, the rest are cleared
+pub fn aam(registers: &mut RegisterFile, base: u8) {
+    let al = registers.get(Register::AL) as u8;
+    registers.set(Register::AH, (al / base) as u16);
+    and(registers, (al % base) as u16, 0xff, false);
+    registers.set(Register::AL, (al % base) as u16);
+}
+
+/// Combines the base `base` digits in AH and AL into AL. The flags come from the final addition
+/// of AL to AH * base, like they do on the 8086
+pub fn aad(registers: &mut RegisterFile, base: u8) {
+    let al = registers.get(Register::AL);
+    let product = (registers.get(Register::AH) as u8).wrapping_mul(base);
+    let result = add(registers, al, product as u16, false);
+    registers.set(Register::AX, result);
+}
+
+/// MUL and IMUL, returning the double width product. CF and OF are set when the upper half of
+/// the product is significant, the other arithmetic flags are left alone
+pub fn mul(flags: &mut RegisterFile, a: u16, b: u16, wide: bool, signed: bool) -> u32 {
+    let (product, significant) = match (wide, signed) {
+        (false, false) => {
+            let product = a as u32 * b as u32;
+            (product, product > 0xff)
+        }
+        (true, false) => {
+            let product = a as u32 * b as u32;
+            (product, product > 0xffff)
+        }
+        (false, true) => {
+            let product = a as i8 as i32 * b as i8 as i32;
+            (product as u32 & 0xffff, product != product as i8 as i32)
+        }
+        (true, true) => {
+            let product = a as i16 as i32 * b as i16 as i32;
+            (product as u32, product != product as i16 as i32)
+        }
+    };
+    flags.set_flag(CpuFlag::CF, significant);
+    flags.set_flag(CpuFlag::OF, significant);
+    product
+}
+
+/// DIV and IDIV of a double width dividend, returning the quotient and remainder or `None` when
+/// the 8086 would raise a divide error. The signed quotient can't be the most negative value,
+/// which the 8086 treats as an overflow
+pub fn div(dividend: u32, divisor: u16, wide: bool, signed: bool) -> Option<(u16, u16)> {
+    if divisor == 0 {
+        return None;
+    }
+    if signed {
+        let (dividend, divisor, limit) = if wide {
+            (dividend as i32 as i64, divisor as i16 as i64, 0x7fff)
+        } else {
+            (dividend as u16 as i16 as i64, divisor as i8 as i64, 0x7f)
+        };
+        let quotient = dividend / divisor;
+        if quotient > limit || quotient < -limit {
+            return None;
+        }
+        let remainder = dividend % divisor;
+        Some((
+            (quotient as u32 & mask(wide)) as u16,
+            (remainder as u32 & mask(wide)) as u16,
+        ))
+    } else {
+        let quotient = dividend / divisor as u32;
+        if quotient > mask(wide) {
+            return None;
+        }
+        Some((quotient as u16, (dividend % divisor as u32) as u16))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{aaa, aad, aam, aas, adc, add, and, daa, das, div, mul, sbb, shift, sub};
+    use crate::cpu::CpuFlag;
+    use crate::instructions::LogicOperator;
+    use crate::register_file::RegisterFile;
+    use crate::registers::Register;
+
+    #[test]
+    fn test_byte_overflow() {
+        let mut flags = RegisterFile::new();
+        assert_eq!(add(&mut flags, 0x7f, 0x01, false), 0x80);
+        assert!(flags.flag(CpuFlag::OF));
+        assert!(flags.flag(CpuFlag::SF));
+        assert!(flags.flag(CpuFlag::AF));
+        assert!(!flags.flag(CpuFlag::CF));
+        flags.set_flag(CpuFlag::CF, true);
+        assert_eq!(adc(&mut flags, 0xff, 0x00, false), 0x00);
+        assert!(flags.flag(CpuFlag::CF));
+        assert!(flags.flag(CpuFlag::ZF));
+        assert!(!flags.flag(CpuFlag::OF));
+    }
+
+    #[test]
+    fn test_borrow() {
+        let mut flags = RegisterFile::new();
+        assert_eq!(sub(&mut flags, 0x0000, 0x0001, true), 0xffff);
+        assert!(flags.flag(CpuFlag::CF));
+        assert!(flags.flag(CpuFlag::SF));
+        assert!(!flags.flag(CpuFlag::OF));
+        assert_eq!(sbb(&mut flags, 0x8000, 0x0000, true), 0x7fff);
+        assert!(flags.flag(CpuFlag::OF));
+        assert!(!flags.flag(CpuFlag::CF));
+        assert_eq!(and(&mut flags, 0x0007, 0x0003, true), 0x0003);
+        assert!(!flags.flag(CpuFlag::OF));
+        assert!(flags.flag(CpuFlag::PF));
+    }
+
+    #[test]
+    fn test_multiply() {
+        let mut flags = RegisterFile::new();
+        assert_eq!(mul(&mut flags, 0x80, 0x02, false, false), 0x0100);
+        assert!(flags.flag(CpuFlag::CF));
+        assert_eq!(mul(&mut flags, 0xff, 0x02, false, true), 0xfffe);
+        assert!(!flags.flag(CpuFlag::OF));
+        assert_eq!(mul(&mut flags, 0x4000, 0x0002, true, true), 0x8000);
+        assert!(flags.flag(CpuFlag::OF));
+        assert_eq!(mul(&mut flags, 0xffff, 0xffff, true, false), 0xfffe0001);
+        assert!(flags.flag(CpuFlag::CF));
+    }
+
+    #[test]
+    fn test_divide() {
+        assert_eq!(div(0x0107, 0x10, false, false), Some((0x10, 0x07)));
+        assert_eq!(div(0x1000, 0x10, false, false), None);
+        assert_eq!(div(0x0007, 0, false, false), None);
+        assert_eq!(div(-7i16 as u16 as u32, 2, false, true), Some((0xfd, 0xff)));
+        assert_eq!(div(-128i16 as u16 as u32, 1, false, true), None);
+        assert_eq!(div(0x0001_0000, 0x0002, true, false), Some((0x8000, 0)));
+        assert_eq!(div(-65534i32 as u32, 0xfffe, true, true), Some((0x7fff, 0)));
+    }
+
+    #[test]
+    fn test_shifts() {
+        let mut flags = RegisterFile::new();
+        assert_eq!(shift(&mut flags, LogicOperator::SHL, 0x81, 1, false), 0x02);
+        assert!(flags.flag(CpuFlag::CF));
+        assert!(flags.flag(CpuFlag::OF));
+        assert_eq!(
+            shift(&mut flags, LogicOperator::SAR, 0x8001, 4, true),
+            0xf800
+        );
+        assert!(!flags.flag(CpuFlag::CF));
+        assert!(flags.flag(CpuFlag::SF));
+        assert_eq!(shift(&mut flags, LogicOperator::SHR, 0x0001, 17, true), 0);
+        assert!(!flags.flag(CpuFlag::CF));
+        assert!(flags.flag(CpuFlag::ZF));
+        assert_eq!(shift(&mut flags, LogicOperator::SHR, 0x80, 1, false), 0x40);
+        assert!(flags.flag(CpuFlag::OF));
+    }
+
+    #[test]
+    fn test_rotates() {
+        let mut flags = RegisterFile::new();
+        assert_eq!(shift(&mut flags, LogicOperator::ROL, 0x81, 1, false), 0x03);
+        assert!(flags.flag(CpuFlag::CF));
+        assert_eq!(
+            shift(&mut flags, LogicOperator::ROR, 0x0001, 1, true),
+            0x8000
+        );
+        assert!(flags.flag(CpuFlag::CF));
+        assert!(flags.flag(CpuFlag::OF));
+        flags.set_flag(CpuFlag::CF, false);
+        assert_eq!(shift(&mut flags, LogicOperator::RCL, 0x80, 1, false), 0x00);
+        assert!(flags.flag(CpuFlag::CF));
+        assert!(!flags.flag(CpuFlag::ZF));
+        assert_eq!(shift(&mut flags, LogicOperator::RCR, 0x00, 9, false), 0x00);
+        assert!(flags.flag(CpuFlag::CF));
+        assert_eq!(shift(&mut flags, LogicOperator::RCL, 0x00, 9, false), 0x00);
+        assert!(flags.flag(CpuFlag::CF));
+    }
+
+    #[test]
+    fn test_decimal_adjust() {
+        let mut registers = RegisterFile::new();
+        // 79 + 35 = 114
+        let sum = add(&mut registers, 0x79, 0x35, false);
+        registers.set(Register::AL, sum);
+        daa(&mut registers);
+        assert_eq!(registers.get(Register::AL), 0x14);
+        assert!(registers.flag(CpuFlag::CF));
+        assert!(registers.flag(CpuFlag::AF));
+        // 35 - 47 = -12, borrowing 1
+        let difference = sub(&mut registers, 0x35, 0x47, false);
+        registers.set(Register::AL, difference);
+        das(&mut registers);
+        assert_eq!(registers.get(Register::AL), 0x88);
+        assert!(registers.flag(CpuFlag::CF));
+        assert!(registers.flag(CpuFlag::SF));
+    }
+
+    #[test]
+    fn test_ascii_adjust() {
+        let mut registers = RegisterFile::new();
+        // '9' + '5' = 14 unpacked
+        let sum = add(&mut registers, 0x39, 0x35, false);
+        registers.set(Register::AX, sum);
+        aaa(&mut registers);
+        assert_eq!(registers.get(Register::AX), 0x0104);
+        assert!(registers.flag(CpuFlag::CF));
+        // 04 - 05 borrows from AH
+        let difference = sub(&mut registers, 0x04, 0x05, false);
+        registers.set(Register::AL, difference);
+        aas(&mut registers);
+        assert_eq!(registers.get(Register::AX), 0x0009);
+        assert!(registers.flag(CpuFlag::CF));
+        registers.set(Register::AX, 0x0705);
+        aad(&mut registers, 10);
+        assert_eq!(registers.get(Register::AX), 75);
+        aam(&mut registers, 16);
+        assert_eq!(registers.get(Register::AX), 0x040b);
+        assert!(!registers.flag(CpuFlag::ZF));
+    }
+}
@@ -0,0 +1,530 @@
+use crate::instructions::Mnemonic;
+use crate::registers::{Register, RegisterMemory};
+
+/// Clocks a word transfer takes on top of the manual's figures when the bus needs two cycles
+/// for it
+const WORD_PENALTY: u32 = 4;
+
+/// The 8088 runs the same instructions as the 8086 but only has an 8-bit data bus, so it moves
+/// every word in two bus cycles. The 8086 only needs two for a word at an odd address
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Variant {
+    #[default]
+    I8086,
+    I8088,
+}
+
+/// The state an estimate depends on besides the instruction itself
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Conditions {
+    pub variant: Variant,
+    /// A segment override prefix applies to the instruction
+    pub segment_override: bool,
+    /// A conditional jump or LOOP jumped, or INTO interrupted
+    pub taken: bool,
+    /// What CL held for a shift or rotate by CL
+    pub shift_count: u8,
+    /// The memory operand, or the string SI or DI points to, is at an odd address
+    pub odd_address: bool,
+}
+
+/// An estimate from the 8086 manual: the clocks of the instruction form plus the clocks of
+/// the effective address calculation for its memory operand and the penalty for word
+/// transfers that take two bus cycles
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Clocks {
+    pub base: u32,
+    pub effective_address: u32,
+    pub penalty: u32,
+}
+
+impl Clocks {
+    fn new(base: u32) -> Self {
+        Self {
+            base,
+            ..Self::default()
+        }
+    }
+
+    /// `base` plus the effective address clocks of `operand`, if it is a memory operand
+    fn with_operand(base: u32, operand: RegisterMemory, conditions: Conditions) -> Self {
+        Self {
+            base,
+            effective_address: effective_address(operand, conditions.segment_override).unwrap_or(0),
+            penalty: 0,
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.base + self.effective_address + self.penalty
+    }
+
+    /// How the total adds up like the reference traces print it, e.g. `8 + 6ea + 4p`, when
+    /// there is more to it than the base clocks
+    pub fn breakdown(&self) -> Option<String> {
+        if self.effective_address == 0 && self.penalty == 0 {
+            return None;
+        }
+        let mut breakdown = self.base.to_string();
+        if self.effective_address != 0 {
+            breakdown += &format!(" + {}ea", self.effective_address);
+        }
+        if self.penalty != 0 {
+            breakdown += &format!(" + {}p", self.penalty);
+        }
+        Some(breakdown)
+    }
+}
+
+/// Clocks to calculate the address of a memory operand, None for registers and immediates.
+/// `[bp]` can only be encoded with a zero displacement, it is counted as plain `[bp]` anyway
+pub fn effective_address(operand: RegisterMemory, segment_override: bool) -> Option<u32> {
+    let clocks = match operand {
+        RegisterMemory::DirectAddress(_) => 6,
+        RegisterMemory::RegisterAddress(_)
+        | RegisterMemory::RegisterData(_, 0)
+        | RegisterMemory::RegisterDataWide(_, 0) => 5,
+        RegisterMemory::RegisterData(..) | RegisterMemory::RegisterDataWide(..) => 9,
+        RegisterMemory::CombineRegisters(base, index) => 7 + pairing(base, index),
+        RegisterMemory::CombineRegistersData(base, index, 0)
+        | RegisterMemory::CombineRegistersDataWide(base, index, 0) => 7 + pairing(base, index),
+        RegisterMemory::CombineRegistersData(base, index, _)
+        | RegisterMemory::CombineRegistersDataWide(base, index, _) => 11 + pairing(base, index),
+        _ => return None,
+    };
+    Some(if segment_override { clocks + 2 } else { clocks })
+}
+
+/// BP+SI and BX+DI take a clock longer than BP+DI and BX+SI
+fn pairing(base: Register, index: Register) -> u32 {
+    match (base, index) {
+        (Register::BP, Register::SI) | (Register::BX, Register::DI) => 1,
+        _ => 0,
+    }
+}
+
+/// Picks between the forms of a two operand instruction, `forms` are the clocks for
+/// reg,reg  reg,mem  mem,reg  reg,imm  and  mem,imm  in that order
+fn operand_forms(
+    dest: RegisterMemory,
+    source: RegisterMemory,
+    forms: [u32; 5],
+    conditions: Conditions,
+) -> Clocks {
+    let immediate = matches!(
+        source,
+        RegisterMemory::Immediate(_) | RegisterMemory::ImmediateWide(_)
+    );
+    match (dest.is_memory(), source.is_memory(), immediate) {
+        (true, _, true) => Clocks::with_operand(forms[4], dest, conditions),
+        (false, _, true) => Clocks::new(forms[3]),
+        (true, ..) => Clocks::with_operand(forms[2], dest, conditions),
+        (false, true, _) => Clocks::with_operand(forms[1], source, conditions),
+        _ => Clocks::new(forms[0]),
+    }
+}
+
+/// Register and memory forms of a single operand instruction
+fn operand_form(
+    operand: RegisterMemory,
+    register: u32,
+    memory: u32,
+    conditions: Conditions,
+) -> Clocks {
+    if operand.is_memory() {
+        Clocks::with_operand(memory, operand, conditions)
+    } else {
+        Clocks::new(register)
+    }
+}
+
+fn branch(taken: u32, not_taken: u32, conditions: Conditions) -> Clocks {
+    Clocks::new(if conditions.taken { taken } else { not_taken })
+}
+
+fn is_accumulator(operand: RegisterMemory) -> bool {
+    matches!(
+        operand,
+        RegisterMemory::Register(Register::AL) | RegisterMemory::Register(Register::AX)
+    )
+}
+
+/// Estimates how long `mnemonic` takes on the variant in `conditions`. Where the manual gives a
+/// range, as it does for multiplication and division, the lowest figure is used
+pub fn estimate(mnemonic: Mnemonic, conditions: Conditions) -> Clocks {
+    let (operand, aligned) = word_transfers(mnemonic, conditions);
+    let penalized = match conditions.variant {
+        Variant::I8086 if conditions.odd_address => operand,
+        Variant::I8086 => 0,
+        Variant::I8088 => operand + aligned,
+    };
+    Clocks {
+        penalty: WORD_PENALTY * penalized,
+        ..manual_clocks(mnemonic, conditions)
+    }
+}
+
+/// The clocks the 8086 manual gives for each instruction form, with aligned word transfers
+fn manual_clocks(mnemonic: Mnemonic, conditions: Conditions) -> Clocks {
+    match mnemonic {
+        // the accumulator has its own shorter encodings for direct addresses
+        Mnemonic::MOV {
+            dest,
+            source: RegisterMemory::DirectAddress(_),
+        } if is_accumulator(dest) => Clocks::new(10),
+        Mnemonic::MOV {
+            dest: RegisterMemory::DirectAddress(_),
+            source,
+        } if is_accumulator(source) => Clocks::new(10),
+        Mnemonic::MOV { dest, source } => operand_forms(dest, source, [2, 8, 9, 4, 10], conditions),
+        Mnemonic::ADD { dest, source }
+        | Mnemonic::ADC { dest, source }
+        | Mnemonic::SUB { dest, source }
+        | Mnemonic::SBB { dest, source }
+        | Mnemonic::AND { dest, source }
+        | Mnemonic::OR { dest, source }
+        | Mnemonic::XOR { dest, source } => {
+            operand_forms(dest, source, [3, 9, 16, 4, 17], conditions)
+        }
+        Mnemonic::CMP { dest, source } => operand_forms(dest, source, [3, 9, 9, 4, 10], conditions),
+        Mnemonic::TEST {
+            dest,
+            source: RegisterMemory::Immediate(_) | RegisterMemory::ImmediateWide(_),
+        } if is_accumulator(dest) => Clocks::new(4),
+        Mnemonic::TEST { dest, source } => {
+            operand_forms(dest, source, [3, 9, 9, 5, 11], conditions)
+        }
+        Mnemonic::XCHG { dest, source } => match (dest, source) {
+            (RegisterMemory::Register(Register::AX), RegisterMemory::Register(_))
+            | (RegisterMemory::Register(_), RegisterMemory::Register(Register::AX)) => {
+                Clocks::new(3)
+            }
+            _ if dest.is_memory() => Clocks::with_operand(17, dest, conditions),
+            _ => operand_forms(dest, source, [4, 17, 17, 4, 17], conditions),
+        },
+        Mnemonic::INC { dest, wide } | Mnemonic::DEC { dest, wide } => {
+            operand_form(dest, if wide { 2 } else { 3 }, 15, conditions)
+        }
+        Mnemonic::NEG { dest, .. } | Mnemonic::NOT { dest, .. } => {
+            operand_form(dest, 3, 16, conditions)
+        }
+        Mnemonic::MUL { dest, source } => {
+            let (register, memory) = match is_wide(dest) {
+                true => (118, 124),
+                false => (70, 76),
+            };
+            operand_form(source, register, memory, conditions)
+        }
+        Mnemonic::IMUL { dest, source } => {
+            let (register, memory) = match is_wide(dest) {
+                true => (128, 134),
+                false => (80, 86),
+            };
+            operand_form(source, register, memory, conditions)
+        }
+        Mnemonic::DIV { dest, source } => {
+            let (register, memory) = match is_wide(dest) {
+                true => (144, 150),
+                false => (80, 86),
+            };
+            operand_form(source, register, memory, conditions)
+        }
+        Mnemonic::IDIV { dest, source } => {
+            let (register, memory) = match is_wide(dest) {
+                true => (165, 171),
+                false => (101, 107),
+            };
+            operand_form(source, register, memory, conditions)
+        }
+        Mnemonic::SAL { dest, source, .. }
+        | Mnemonic::SAR { dest, source, .. }
+        | Mnemonic::SHR { dest, source, .. }
+        | Mnemonic::ROL { dest, source, .. }
+        | Mnemonic::ROR { dest, source, .. }
+        | Mnemonic::RCL { dest, source, .. }
+        | Mnemonic::RCR { dest, source, .. } => match source {
+            RegisterMemory::Register(Register::CL) => {
+                let bits = 4 * conditions.shift_count as u32;
+                operand_form(dest, 8 + bits, 20 + bits, conditions)
+            }
+            _ => operand_form(dest, 2, 15, conditions),
+        },
+        Mnemonic::DAA | Mnemonic::DAS | Mnemonic::AAA | Mnemonic::AAS => Clocks::new(4),
+        Mnemonic::AAM { .. } => Clocks::new(83),
+        Mnemonic::AAD { .. } => Clocks::new(60),
+        Mnemonic::CBW => Clocks::new(2),
+        Mnemonic::CWD => Clocks::new(5),
+        Mnemonic::LAHF | Mnemonic::SAHF => Clocks::new(4),
+        Mnemonic::XLAT => Clocks::new(11),
+        Mnemonic::LEA { source, .. } => Clocks::with_operand(2, source, conditions),
+        Mnemonic::LDS { source, .. } | Mnemonic::LES { source, .. } => {
+            Clocks::with_operand(16, source, conditions)
+        }
+        Mnemonic::IN { source, .. } | Mnemonic::OUT { source, .. } => match source {
+            RegisterMemory::Register(Register::DX) => Clocks::new(8),
+            _ => Clocks::new(10),
+        },
+        Mnemonic::PUSH(source) => operand_form(source, 11, 16, conditions),
+        Mnemonic::POP(dest) => operand_form(dest, 8, 17, conditions),
+        Mnemonic::PUSHSEG(_) | Mnemonic::PUSHF => Clocks::new(10),
+        Mnemonic::POPSEG(_) | Mnemonic::POPF => Clocks::new(8),
+        Mnemonic::CALL {
+            far_proc: Some(_), ..
+        } => Clocks::new(28),
+        Mnemonic::CALL { .. } => Clocks::new(19),
+        Mnemonic::CALLINDIRECT { target, far: true } => {
+            Clocks::with_operand(37, target, conditions)
+        }
+        Mnemonic::CALLINDIRECT { target, .. } => operand_form(target, 16, 21, conditions),
+        Mnemonic::JMP { .. } | Mnemonic::JMPFAR { .. } => Clocks::new(15),
+        Mnemonic::JMPINDIRECT { target, far: true } => Clocks::with_operand(24, target, conditions),
+        Mnemonic::JMPINDIRECT { target, .. } => operand_form(target, 11, 18, conditions),
+        Mnemonic::RET { segment: None } => Clocks::new(8),
+        Mnemonic::RET { .. } => Clocks::new(12),
+        Mnemonic::RETF { segment: None } => Clocks::new(18),
+        Mnemonic::RETF { .. } => Clocks::new(17),
+        Mnemonic::JO { .. }
+        | Mnemonic::JNO { .. }
+        | Mnemonic::JB { .. }
+        | Mnemonic::JNB { .. }
+        | Mnemonic::JE { .. }
+        | Mnemonic::JNE { .. }
+        | Mnemonic::JBE { .. }
+        | Mnemonic::JNBE { .. }
+        | Mnemonic::JS { .. }
+        | Mnemonic::JNS { .. }
+        | Mnemonic::JP { .. }
+        | Mnemonic::JNP { .. }
+        | Mnemonic::JL { .. }
+        | Mnemonic::JNL { .. }
+        | Mnemonic::JLE { .. }
+        | Mnemonic::JNLE { .. } => branch(16, 4, conditions),
+        Mnemonic::JCXZ { .. } => branch(18, 6, conditions),
+        Mnemonic::LOOP { .. } => branch(17, 5, conditions),
+        Mnemonic::LOOPE { .. } => branch(18, 6, conditions),
+        Mnemonic::LOOPNE { .. } => branch(19, 5, conditions),
+        Mnemonic::INT { value: 3 } => Clocks::new(52),
+        Mnemonic::INT { .. } => Clocks::new(51),
+        Mnemonic::INTO => branch(53, 4, conditions),
+        Mnemonic::IRET => Clocks::new(24),
+        Mnemonic::MOVS { .. } => Clocks::new(18),
+        Mnemonic::CMPS { .. } => Clocks::new(22),
+        Mnemonic::SCAS { .. } => Clocks::new(15),
+        Mnemonic::LODS { .. } => Clocks::new(12),
+        Mnemonic::STOS { .. } => Clocks::new(11),
+        Mnemonic::CLC
+        | Mnemonic::STC
+        | Mnemonic::CMC
+        | Mnemonic::CLD
+        | Mnemonic::STD
+        | Mnemonic::CLI
+        | Mnemonic::STI
+        | Mnemonic::HLT
+        | Mnemonic::LOCK
+        | Mnemonic::REP
+        | Mnemonic::REPNE
+        | Mnemonic::ESC => Clocks::new(2),
+        Mnemonic::NOP | Mnemonic::WAIT => Clocks::new(3),
+        // a segment override is counted in the effective address of the instruction it applies to
+        _ => Clocks::default(),
+    }
+}
+
+fn is_wide(accumulator: RegisterMemory) -> bool {
+    accumulator.is_wide().unwrap_or(true)
+}
+
+/// Word transfers to the memory operand or string and to the places that are taken to be word
+/// aligned: the stack, the interrupt vector table and I/O ports. Byte transfers take a single
+/// bus cycle on both variants so they aren't counted
+fn word_transfers(mnemonic: Mnemonic, conditions: Conditions) -> (u32, u32) {
+    let words = |wide: bool, transfers: u32| if wide { transfers } else { 0 };
+    let memory = |operand: RegisterMemory, wide: bool, transfers: u32| {
+        words(wide && operand.is_memory(), transfers)
+    };
+    let width = |dest: RegisterMemory, source: RegisterMemory| {
+        dest.is_wide().or(source.is_wide()).unwrap_or(false)
+    };
+    match mnemonic {
+        Mnemonic::MOV { dest, source }
+        | Mnemonic::CMP { dest, source }
+        | Mnemonic::TEST { dest, source } => {
+            let wide = width(dest, source);
+            (memory(dest, wide, 1) + memory(source, wide, 1), 0)
+        }
+        Mnemonic::ADD { dest, source }
+        | Mnemonic::ADC { dest, source }
+        | Mnemonic::SUB { dest, source }
+        | Mnemonic::SBB { dest, source }
+        | Mnemonic::AND { dest, source }
+        | Mnemonic::OR { dest, source }
+        | Mnemonic::XOR { dest, source } => {
+            let wide = width(dest, source);
+            (memory(dest, wide, 2) + memory(source, wide, 1), 0)
+        }
+        Mnemonic::XCHG { dest, source } => {
+            let wide = width(dest, source);
+            (memory(dest, wide, 2) + memory(source, wide, 2), 0)
+        }
+        Mnemonic::INC { dest, wide }
+        | Mnemonic::DEC { dest, wide }
+        | Mnemonic::NEG { dest, wide }
+        | Mnemonic::NOT { dest, wide }
+        | Mnemonic::SAL { dest, wide, .. }
+        | Mnemonic::SAR { dest, wide, .. }
+        | Mnemonic::SHR { dest, wide, .. }
+        | Mnemonic::ROL { dest, wide, .. }
+        | Mnemonic::ROR { dest, wide, .. }
+        | Mnemonic::RCL { dest, wide, .. }
+        | Mnemonic::RCR { dest, wide, .. } => (memory(dest, wide, 2), 0),
+        Mnemonic::MUL { dest, source }
+        | Mnemonic::IMUL { dest, source }
+        | Mnemonic::DIV { dest, source }
+        | Mnemonic::IDIV { dest, source } => (memory(source, is_wide(dest), 1), 0),
+        Mnemonic::LDS { source, .. } | Mnemonic::LES { source, .. } => (memory(source, true, 2), 0),
+        Mnemonic::PUSH(operand) | Mnemonic::POP(operand) => (memory(operand, true, 1), 1),
+        Mnemonic::PUSHSEG(_) | Mnemonic::POPSEG(_) | Mnemonic::PUSHF | Mnemonic::POPF => (0, 1),
+        Mnemonic::CALL {
+            far_proc: Some(_), ..
+        } => (0, 2),
+        Mnemonic::CALL { .. } | Mnemonic::RET { .. } => (0, 1),
+        Mnemonic::RETF { .. } => (0, 2),
+        Mnemonic::CALLINDIRECT { target, far: true } => (memory(target, true, 2), 2),
+        Mnemonic::CALLINDIRECT { target, .. } => (memory(target, true, 1), 1),
+        Mnemonic::JMPINDIRECT { target, far: true } => (memory(target, true, 2), 0),
+        Mnemonic::JMPINDIRECT { target, .. } => (memory(target, true, 1), 0),
+        Mnemonic::INT { .. } => (0, 5),
+        Mnemonic::INTO if conditions.taken => (0, 5),
+        Mnemonic::IRET => (0, 3),
+        Mnemonic::IN { dest, .. } | Mnemonic::OUT { dest, .. } => (0, words(is_wide(dest), 1)),
+        Mnemonic::MOVS { wide } | Mnemonic::CMPS { wide } => (words(wide, 2), 0),
+        Mnemonic::SCAS { wide } | Mnemonic::LODS { wide } | Mnemonic::STOS { wide } => {
+            (words(wide, 1), 0)
+        }
+        _ => (0, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{effective_address, estimate, Clocks, Conditions, Variant};
+    use crate::instructions::Mnemonic;
+    use crate::registers::{Register, RegisterMemory};
+
+    #[test]
+    fn test_effective_address() {
+        let cases = [
+            (RegisterMemory::DirectAddress(1000), 6),
+            (RegisterMemory::RegisterAddress(Register::SI), 5),
+            (RegisterMemory::RegisterData(Register::BP, 0), 5),
+            (RegisterMemory::RegisterDataWide(Register::BX, 1000), 9),
+            (
+                RegisterMemory::CombineRegisters(Register::BP, Register::DI),
+                7,
+            ),
+            (
+                RegisterMemory::CombineRegisters(Register::BX, Register::DI),
+                8,
+            ),
+            (
+                RegisterMemory::CombineRegistersData(Register::BX, Register::SI, -4),
+                11,
+            ),
+            (
+                RegisterMemory::CombineRegistersDataWide(Register::BP, Register::SI, 1000),
+                12,
+            ),
+        ];
+        for (operand, clocks) in cases {
+            assert_eq!(
+                effective_address(operand, false),
+                Some(clocks),
+                "{:?}",
+                operand
+            );
+            assert_eq!(
+                effective_address(operand, true),
+                Some(clocks + 2),
+                "{:?}",
+                operand
+            );
+        }
+        assert_eq!(
+            effective_address(RegisterMemory::Register(Register::AX), false),
+            None
+        );
+    }
+
+    #[test]
+    fn test_instruction_forms() {
+        let conditions = Conditions::default();
+        let memory = RegisterMemory::RegisterAddress(Register::BX);
+        let add = Mnemonic::ADD {
+            dest: memory,
+            source: RegisterMemory::Immediate(1),
+        };
+        assert_eq!(estimate(add, conditions).total(), 22);
+        assert_eq!(estimate(add, conditions).breakdown().unwrap(), "17 + 5ea");
+        // the accumulator forms of MOV don't have an address calculation
+        let load = Mnemonic::MOV {
+            dest: RegisterMemory::Register(Register::AX),
+            source: RegisterMemory::DirectAddress(1000),
+        };
+        assert_eq!(estimate(load, conditions), Clocks::new(10));
+        let shift = Mnemonic::SAL {
+            dest: memory,
+            source: RegisterMemory::Register(Register::CL),
+            wide: true,
+        };
+        let by_three = Conditions {
+            shift_count: 3,
+            ..conditions
+        };
+        assert_eq!(estimate(shift, by_three).total(), 20 + 12 + 5);
+        let jump = Mnemonic::JNE { label: 0xfe };
+        let taken = Conditions {
+            taken: true,
+            ..conditions
+        };
+        assert_eq!(estimate(jump, conditions).total(), 4);
+        assert_eq!(estimate(jump, taken).total(), 16);
+        assert_eq!(estimate(jump, taken).breakdown(), None);
+    }
+
+    #[test]
+    fn test_word_transfers() {
+        let i8086 = Conditions::default();
+        let i8088 = Conditions {
+            variant: Variant::I8088,
+            ..i8086
+        };
+        let push = Mnemonic::PUSH(RegisterMemory::Register(Register::CX));
+        assert_eq!(estimate(push, i8086).breakdown(), None);
+        assert_eq!(estimate(push, i8088).breakdown().unwrap(), "11 + 4p");
+        // a byte takes one bus cycle on either variant, at any address
+        let increment = Mnemonic::INC {
+            dest: RegisterMemory::DirectAddress(1001),
+            wide: false,
+        };
+        let odd = Conditions {
+            odd_address: true,
+            ..i8088
+        };
+        assert_eq!(estimate(increment, odd).total(), 15 + 6);
+        let increment = Mnemonic::INC {
+            dest: RegisterMemory::DirectAddress(1001),
+            wide: true,
+        };
+        assert_eq!(
+            estimate(increment, odd).breakdown().unwrap(),
+            "15 + 6ea + 8p"
+        );
+        let odd = Conditions {
+            odd_address: true,
+            ..i8086
+        };
+        assert_eq!(estimate(increment, odd).total(), 15 + 6 + 8);
+        let copy = Mnemonic::MOVS { wide: true };
+        assert_eq!(estimate(copy, odd).breakdown().unwrap(), "18 + 8p");
+    }
+}
@@ -1,10 +1,20 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 
-use crate::instructions::Mnemonic;
+use crate::alu;
+use crate::clocks::{self, Clocks, Conditions, Variant};
+use crate::dataflow::Location;
+use crate::instructions::{Instruction, LogicOperator, Mnemonic};
+use crate::io::IoBus;
+use crate::memory::Memory;
+use crate::opcodes::decode;
+use crate::pic::Pic;
+use crate::register_file::RegisterFile;
 use crate::registers::{Register, RegisterMemory, SegmentRegister};
 use crate::Result;
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
 pub enum CpuFlag {
     PF,
     ZF,
@@ -17,76 +27,1443 @@ pub enum CpuFlag {
     CF
 }
 
+/// Works out whether a two operand instruction operates on bytes or words, at least one of
+/// the operands has to have an implied width
+fn operand_width(dest: RegisterMemory, source: RegisterMemory) -> Result<bool> {
+    dest.is_wide()
+        .or(source.is_wide())
+        .ok_or_else(|| format!("Unknown operand width for {:?}, {:?}", dest, source).into())
+}
+
+/// The longest 8086 instruction without prefixes: opcode, mod r/m, two displacement bytes and
+/// two bytes of immediate data
+const MAX_INSTRUCTION_LENGTH: u16 = 6;
+
+/// How much time passes on a step while halted, the cpu just waits for an interrupt
+const HALTED_CLOCKS: u32 = 10;
+
+/// A Rust interrupt service routine, it gets the whole `Cpu` so it can read and update the
+/// registers and memory and then returns straight to the interrupted program
+pub type InterruptHandler = Box<dyn FnMut(&mut Cpu) -> Result<()>>;
+
+/// An `InterruptHandler` for `vector`, restricted to one service when `function` is set.
+/// DOS and BIOS services select the function through AH
+struct InterruptHook {
+    vector: u8,
+    function: Option<u8>,
+    handler: InterruptHandler,
+}
+
+impl InterruptHook {
+    fn matches(&self, vector: u8, function: u8) -> bool {
+        self.vector == vector && self.function.is_none_or(|expected| expected == function)
+    }
+}
+
+impl std::fmt::Debug for InterruptHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InterruptHook")
+            .field("vector", &self.vector)
+            .field("function", &self.function)
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug)]
 pub struct Cpu {
-    flags: HashMap<CpuFlag, bool>,
-    pub registers: HashMap<Register, isize>,
-    segment_registers: HashMap<SegmentRegister, isize>
+    pub registers: RegisterFile,
+    segment_registers: HashMap<SegmentRegister, u16>,
+    pub memory: Memory,
+    pub io: IoBus,
+    segment_override: Option<SegmentRegister>,
+    interrupt_hooks: Vec<InterruptHook>,
+    interrupt_controller: Option<Rc<RefCell<Pic>>>,
+    /// Set for the instruction after one that can't be interrupted until the next one is done
+    interrupt_shadow: bool,
+    halted: bool,
+    /// Whether the last conditional jump, LOOP or INTO transferred control
+    branch_taken: bool,
+    variant: Variant,
+    clocks: u64,
+    last_clocks: Clocks,
 }
 
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Cpu {
     pub fn new() -> Self {
-        let mut segment_registers: HashMap<SegmentRegister, isize> = HashMap::new();
+        let mut segment_registers: HashMap<SegmentRegister, u16> = HashMap::new();
         segment_registers.insert(SegmentRegister::ES, 0);
         segment_registers.insert(SegmentRegister::CS, 0);
         segment_registers.insert(SegmentRegister::SS, 0);
         segment_registers.insert(SegmentRegister::DS, 0);
-        let mut registers: HashMap<Register, isize> = HashMap::new();
-        registers.insert(Register::AL, 0);
-        registers.insert(Register::CL, 0);
-        registers.insert(Register::BL, 0);
-        registers.insert(Register::DL, 0);
-        registers.insert(Register::AH, 0);
-        registers.insert(Register::CH, 0);
-        registers.insert(Register::DH, 0);
-        registers.insert(Register::BH, 0);
-        registers.insert(Register::AX, 0);
-        registers.insert(Register::CX, 0);
-        registers.insert(Register::BX, 0);
-        registers.insert(Register::DX, 0);
-        registers.insert(Register::SP, 0);
-        registers.insert(Register::BP, 0);
-        registers.insert(Register::SI, 0);
-        registers.insert(Register::DI, 0);
-        let mut flags: HashMap<CpuFlag, bool> = HashMap::new();
-        flags.insert(CpuFlag::PF, false);
-        flags.insert(CpuFlag::ZF, false);
-        flags.insert(CpuFlag::OF, false);
-        flags.insert(CpuFlag::DF, false);
-        flags.insert(CpuFlag::IF, false);
-        flags.insert(CpuFlag::TF, false);
-        flags.insert(CpuFlag::SF, false);
-        flags.insert(CpuFlag::AF, false);
-        flags.insert(CpuFlag::CF, false);
         Self {
-            flags,
-            registers,
-            segment_registers
+            registers: RegisterFile::new(),
+            segment_registers,
+            memory: Memory::new(),
+            io: IoBus::new(),
+            segment_override: None,
+            interrupt_hooks: Vec::new(),
+            interrupt_controller: None,
+            interrupt_shadow: false,
+            halted: false,
+            branch_taken: false,
+            variant: Variant::default(),
+            clocks: 0,
+            last_clocks: Clocks::default(),
+        }
+    }
+    /// Services `vector` with `handler` instead of the interrupt vector table, only when AH is
+    /// `function` if one is given. The most recently registered matching hook wins
+    pub fn hook_interrupt<F>(&mut self, vector: u8, function: Option<u8>, handler: F)
+    where
+        F: FnMut(&mut Cpu) -> Result<()> + 'static,
+    {
+        self.interrupt_hooks.push(InterruptHook {
+            vector,
+            function,
+            handler: Box::new(handler),
+        });
+    }
+    /// Hardware interrupts come from `pic`, they are taken between instructions while IF is set
+    pub fn connect_interrupt_controller(&mut self, pic: Rc<RefCell<Pic>>) {
+        self.interrupt_controller = Some(pic);
+    }
+    /// Copies a flat binary to CS:IP, which is where `step` starts fetching from
+    pub fn load(&mut self, program: &[u8]) {
+        let segment = self.segment_registers[&SegmentRegister::CS];
+        self.memory.load(segment, self.registers.ip, program);
+    }
+    /// Fetches and decodes the instruction at CS:IP, moves IP past it and then executes it, so
+    /// branches are relative to the next instruction just like on the real hardware. With TF
+    /// set the single step trap, interrupt 1, follows and then any pending hardware interrupt,
+    /// while halted a step only lets time pass. Devices are ticked by the estimated clocks
+    pub fn step(&mut self) -> Result<Instruction> {
+        let segment = self.segment_registers[&SegmentRegister::CS];
+        let ip = self.registers.ip;
+        if self.halted {
+            self.count_clocks(Clocks {
+                base: HALTED_CLOCKS,
+                ..Clocks::default()
+            });
+            self.service_interrupt()?;
+            return Ok(Instruction {
+                offset: ip.wrapping_sub(1) as usize,
+                length: 1,
+                mnemonic: Mnemonic::HLT,
+            });
+        }
+        let bytes: Vec<u8> = (0..MAX_INSTRUCTION_LENGTH)
+            .map(|i| self.memory.read_byte(segment, ip.wrapping_add(i)))
+            .collect();
+        let (mnemonic, length) = decode(&bytes)?;
+        self.registers.ip = ip.wrapping_add(length as u16);
+        // the trap depends on TF before the instruction, so the instruction after a POPF or
+        // IRET that sets it is the first one traced and one that clears it is still traced
+        let traced = self.flag(CpuFlag::TF);
+        let mut conditions = Conditions {
+            variant: self.variant,
+            segment_override: self.segment_override.is_some(),
+            taken: false,
+            shift_count: self.registers.get(Register::CL) as u8,
+            odd_address: self.odd_address(mnemonic),
+        };
+        self.branch_taken = false;
+        self.execute(mnemonic)?;
+        conditions.taken = self.branch_taken;
+        self.count_clocks(clocks::estimate(mnemonic, conditions));
+        if traced && self.segment_override.is_none() {
+            self.halted = false;
+            self.interrupt(1)?;
         }
+        self.service_interrupt()?;
+        Ok(Instruction {
+            offset: ip as usize,
+            length,
+            mnemonic,
+        })
+    }
+    /// Takes the highest priority hardware interrupt, which also wakes the cpu up from HLT.
+    /// Nothing is taken between a prefix and its instruction or right after an instruction
+    /// that sets up SS:SP or enables interrupts
+    fn service_interrupt(&mut self) -> Result<()> {
+        if !self.flag(CpuFlag::IF) || self.interrupt_shadow || self.segment_override.is_some() {
+            return Ok(());
+        }
+        let vector = match &self.interrupt_controller {
+            Some(pic) => pic.borrow_mut().acknowledge(),
+            None => None,
+        };
+        if let Some(vector) = vector {
+            self.halted = false;
+            self.interrupt(vector)?;
+        }
+        Ok(())
     }
     pub fn execute(&mut self, instruction: Mnemonic) -> Result<()> {
         match instruction {
-            Mnemonic::MOV {dest, source} => {
-                self.mov(dest, source)?;
+            // a segment override prefix applies to the instruction that follows it
+            Mnemonic::SEGMENTOVERRIDE(segment) => {
+                self.segment_override = Some(segment);
+                return Ok(());
+            }
+            Mnemonic::MOV { dest, source } => self.mov(dest, source)?,
+            Mnemonic::ADD { dest, source } => self.alu(dest, source, true, alu::add)?,
+            Mnemonic::ADC { dest, source } => self.alu(dest, source, true, alu::adc)?,
+            Mnemonic::SUB { dest, source } => self.alu(dest, source, true, alu::sub)?,
+            Mnemonic::SBB { dest, source } => self.alu(dest, source, true, alu::sbb)?,
+            Mnemonic::CMP { dest, source } => self.alu(dest, source, false, alu::sub)?,
+            Mnemonic::AND { dest, source } => self.alu(dest, source, true, alu::and)?,
+            Mnemonic::OR { dest, source } => self.alu(dest, source, true, alu::or)?,
+            Mnemonic::XOR { dest, source } => self.alu(dest, source, true, alu::xor)?,
+            Mnemonic::TEST { dest, source } => self.alu(dest, source, false, alu::and)?,
+            Mnemonic::INC { dest, wide } => self.inc_dec(dest, wide, true)?,
+            Mnemonic::DEC { dest, wide } => self.inc_dec(dest, wide, false)?,
+            Mnemonic::NEG { dest, wide } => {
+                let value = self.read_operand(dest, wide)?;
+                let result = alu::sub(&mut self.registers, 0, value, wide);
+                self.write_operand(dest, result, wide)?;
+            }
+            Mnemonic::NOT { dest, wide } => {
+                let value = self.read_operand(dest, wide)?;
+                self.write_operand(dest, !value, wide)?;
+            }
+            Mnemonic::MUL { dest, source } => self.multiply(dest, source, false)?,
+            Mnemonic::IMUL { dest, source } => self.multiply(dest, source, true)?,
+            Mnemonic::DIV { dest, source } => self.divide(dest, source, false)?,
+            Mnemonic::IDIV { dest, source } => self.divide(dest, source, true)?,
+            Mnemonic::SAL { dest, source, wide } => {
+                self.shift(LogicOperator::SHL, dest, source, wide)?
+            }
+            Mnemonic::SHR { dest, source, wide } => {
+                self.shift(LogicOperator::SHR, dest, source, wide)?
+            }
+            Mnemonic::SAR { dest, source, wide } => {
+                self.shift(LogicOperator::SAR, dest, source, wide)?
+            }
+            Mnemonic::ROL { dest, source, wide } => {
+                self.shift(LogicOperator::ROL, dest, source, wide)?
+            }
+            Mnemonic::ROR { dest, source, wide } => {
+                self.shift(LogicOperator::ROR, dest, source, wide)?
+            }
+            Mnemonic::RCL { dest, source, wide } => {
+                self.shift(LogicOperator::RCL, dest, source, wide)?
+            }
+            Mnemonic::RCR { dest, source, wide } => {
+                self.shift(LogicOperator::RCR, dest, source, wide)?
+            }
+            Mnemonic::DAA => alu::daa(&mut self.registers),
+            Mnemonic::DAS => alu::das(&mut self.registers),
+            Mnemonic::AAA => alu::aaa(&mut self.registers),
+            Mnemonic::AAS => alu::aas(&mut self.registers),
+            // a zero base is a divide error just like DIV
+            Mnemonic::AAM { base: 0 } => self.interrupt(0)?,
+            Mnemonic::AAM { base } => alu::aam(&mut self.registers, base),
+            Mnemonic::AAD { base } => alu::aad(&mut self.registers, base),
+            Mnemonic::LAHF => {
+                let flags = self.registers.flags();
+                self.registers.set(Register::AH, flags & 0xff);
+            }
+            Mnemonic::SAHF => {
+                let flags = (self.registers.flags() & 0xff00) | self.registers.get(Register::AH);
+                self.registers.set_flags(flags);
+            }
+            Mnemonic::CBW => {
+                let al = self.registers.get(Register::AL);
+                self.registers.set(Register::AX, al as u8 as i8 as u16);
+            }
+            Mnemonic::CWD => {
+                let negative = self.registers.get(Register::AX) & 0x8000 != 0;
+                self.registers
+                    .set(Register::DX, if negative { 0xffff } else { 0 });
+            }
+            Mnemonic::XLAT => {
+                // a segment override replaces DS for the table, BX + AL wraps within it
+                let segment = self.segment_override.unwrap_or(SegmentRegister::DS);
+                let offset = self
+                    .registers
+                    .get(Register::BX)
+                    .wrapping_add(self.registers.get(Register::AL));
+                let value = self
+                    .memory
+                    .read_byte(self.segment_registers[&segment], offset);
+                self.registers.set(Register::AL, value as u16);
+            }
+            Mnemonic::CLC => self.registers.set_flag(CpuFlag::CF, false),
+            Mnemonic::STC => self.registers.set_flag(CpuFlag::CF, true),
+            Mnemonic::CMC => self
+                .registers
+                .set_flag(CpuFlag::CF, !self.flag(CpuFlag::CF)),
+            Mnemonic::CLD => self.registers.set_flag(CpuFlag::DF, false),
+            Mnemonic::STD => self.registers.set_flag(CpuFlag::DF, true),
+            Mnemonic::CLI => self.registers.set_flag(CpuFlag::IF, false),
+            Mnemonic::STI => self.registers.set_flag(CpuFlag::IF, true),
+            Mnemonic::HLT => self.halt(),
+            // the port is either an immediate byte or DX, and AL or AX picks the access width
+            Mnemonic::IN { dest, source } => {
+                let port = self.read_operand(source, true)?;
+                let value = match dest {
+                    RegisterMemory::Register(Register::AX) => self.io.read_word(port),
+                    _ => self.io.read_byte(port) as u16,
+                };
+                self.write_operand(dest, value, false)?;
+            }
+            Mnemonic::OUT { dest, source } => {
+                let port = self.read_operand(source, true)?;
+                let value = self.read_operand(dest, false)?;
+                match dest {
+                    RegisterMemory::Register(Register::AX) => self.io.write_word(port, value),
+                    _ => self.io.write_byte(port, value as u8),
+                }
+            }
+            Mnemonic::LEA { dest, source } => {
+                let (_, offset) = self.effective_address(source)?;
+                self.write_operand(dest, offset, true)?;
+            }
+            Mnemonic::LDS { dest, source } => {
+                self.load_far_pointer(dest, source, SegmentRegister::DS)?
+            }
+            Mnemonic::LES { dest, source } => {
+                self.load_far_pointer(dest, source, SegmentRegister::ES)?
+            }
+            Mnemonic::XCHG { dest, source } => {
+                let wide = operand_width(dest, source)?;
+                let a = self.read_operand(dest, wide)?;
+                let b = self.read_operand(source, wide)?;
+                self.write_operand(dest, b, wide)?;
+                self.write_operand(source, a, wide)?;
+            }
+            Mnemonic::INT { value } => self.interrupt(value)?,
+            // INTO only interrupts on overflow, otherwise it falls through as a no-op
+            Mnemonic::INTO if self.flag(CpuFlag::OF) => {
+                self.branch_taken = true;
+                self.interrupt(4)?
+            }
+            Mnemonic::IRET => {
+                self.registers.ip = self.pop();
+                let code_segment = self.pop();
+                self.segment_registers
+                    .insert(SegmentRegister::CS, code_segment);
+                let flags = self.pop();
+                self.registers.set_flags(flags);
+            }
+            Mnemonic::JO { label } => self.jump_if(self.flag(CpuFlag::OF), label),
+            Mnemonic::JNO { label } => self.jump_if(!self.flag(CpuFlag::OF), label),
+            Mnemonic::JB { label } => self.jump_if(self.flag(CpuFlag::CF), label),
+            Mnemonic::JNB { label } => self.jump_if(!self.flag(CpuFlag::CF), label),
+            Mnemonic::JE { label } => self.jump_if(self.flag(CpuFlag::ZF), label),
+            Mnemonic::JNE { label } => self.jump_if(!self.flag(CpuFlag::ZF), label),
+            Mnemonic::JBE { label } => {
+                self.jump_if(self.flag(CpuFlag::CF) || self.flag(CpuFlag::ZF), label)
+            }
+            Mnemonic::JNBE { label } => {
+                self.jump_if(!(self.flag(CpuFlag::CF) || self.flag(CpuFlag::ZF)), label)
+            }
+            Mnemonic::JS { label } => self.jump_if(self.flag(CpuFlag::SF), label),
+            Mnemonic::JNS { label } => self.jump_if(!self.flag(CpuFlag::SF), label),
+            Mnemonic::JP { label } => self.jump_if(self.flag(CpuFlag::PF), label),
+            Mnemonic::JNP { label } => self.jump_if(!self.flag(CpuFlag::PF), label),
+            Mnemonic::JL { label } => self.jump_if(self.less(), label),
+            Mnemonic::JNL { label } => self.jump_if(!self.less(), label),
+            Mnemonic::JLE { label } => self.jump_if(self.less() || self.flag(CpuFlag::ZF), label),
+            Mnemonic::JNLE { label } => {
+                self.jump_if(!(self.less() || self.flag(CpuFlag::ZF)), label)
+            }
+            Mnemonic::JCXZ { label } => self.jump_if(self.registers.get(Register::CX) == 0, label),
+            Mnemonic::LOOP { short_label } => {
+                let count = self.decrement_count();
+                self.jump_if(count != 0, short_label)
+            }
+            Mnemonic::LOOPE { short_label } => {
+                let count = self.decrement_count();
+                self.jump_if(count != 0 && self.flag(CpuFlag::ZF), short_label)
+            }
+            Mnemonic::LOOPNE { short_label } => {
+                let count = self.decrement_count();
+                self.jump_if(count != 0 && !self.flag(CpuFlag::ZF), short_label)
+            }
+            Mnemonic::JMP { label } => {
+                self.registers.ip = self.registers.ip.wrapping_add(label as u16);
             }
-            _ => ()
+            Mnemonic::JMPFAR { segment, offset } => {
+                self.segment_registers.insert(SegmentRegister::CS, segment);
+                self.registers.ip = offset;
+            }
+            Mnemonic::JMPINDIRECT { target, far } => {
+                let (segment, offset) = self.branch_target(target, far)?;
+                self.segment_registers.insert(SegmentRegister::CS, segment);
+                self.registers.ip = offset;
+            }
+            Mnemonic::PUSH(source) => {
+                // the 8086 pushes the value SP has after it has been decremented
+                let value = if source == RegisterMemory::Register(Register::SP) {
+                    self.registers.get(Register::SP).wrapping_sub(2)
+                } else {
+                    self.read_operand(source, true)?
+                };
+                self.push(value);
+            }
+            Mnemonic::POP(dest) => {
+                let value = self.pop();
+                self.write_operand(dest, value, true)?;
+            }
+            Mnemonic::PUSHSEG(segment) => self.push(self.segment_registers[&segment]),
+            Mnemonic::POPSEG(segment) => {
+                let value = self.pop();
+                self.segment_registers.insert(segment, value);
+            }
+            Mnemonic::PUSHF => self.push(self.registers.flags()),
+            Mnemonic::POPF => {
+                let value = self.pop();
+                self.registers.set_flags(value);
+            }
+            Mnemonic::CALL {
+                near_proc: Some(displacement),
+                ..
+            } => {
+                self.push(self.registers.ip);
+                self.registers.ip = self.registers.ip.wrapping_add(displacement as u16);
+            }
+            Mnemonic::CALL {
+                far_proc: Some((segment, offset)),
+                ..
+            } => self.call_far(segment, offset),
+            Mnemonic::CALLINDIRECT { target, far } => {
+                let (segment, offset) = self.branch_target(target, far)?;
+                if far {
+                    self.call_far(segment, offset);
+                } else {
+                    self.push(self.registers.ip);
+                    self.registers.ip = offset;
+                }
+            }
+            Mnemonic::RET { segment } => {
+                self.registers.ip = self.pop();
+                self.release_stack(segment);
+            }
+            Mnemonic::RETF { segment } => {
+                self.registers.ip = self.pop();
+                let code_segment = self.pop();
+                self.segment_registers
+                    .insert(SegmentRegister::CS, code_segment);
+                self.release_stack(segment);
+            }
+            _ => (),
         }
+        self.interrupt_shadow = matches!(
+            instruction,
+            Mnemonic::STI
+                | Mnemonic::POPSEG(SegmentRegister::SS)
+                | Mnemonic::MOV {
+                    dest: RegisterMemory::SegmentRegister(SegmentRegister::SS),
+                    ..
+                }
+        );
+        self.segment_override = None;
         Ok(())
     }
+    /// Resolves a memory operand to the `segment:offset` pair it refers to, offsets are computed
+    /// in 16 bits so they wrap around within the segment
+    pub fn effective_address(&self, operand: RegisterMemory) -> Result<(u16, u16)> {
+        let offset = match operand {
+            RegisterMemory::DirectAddress(address) => address,
+            RegisterMemory::RegisterAddress(register) => self.registers.get(register),
+            RegisterMemory::CombineRegisters(base, index) => {
+                self.registers.get(base).wrapping_add(self.registers.get(index))
+            }
+            RegisterMemory::RegisterData(register, displacement) => self
+                .registers
+                .get(register)
+                .wrapping_add(displacement as u16),
+            RegisterMemory::RegisterDataWide(register, displacement) => self
+                .registers
+                .get(register)
+                .wrapping_add(displacement as u16),
+            RegisterMemory::CombineRegistersData(base, index, displacement) => self
+                .registers
+                .get(base)
+                .wrapping_add(self.registers.get(index))
+                .wrapping_add(displacement as u16),
+            RegisterMemory::CombineRegistersDataWide(base, index, displacement) => self
+                .registers
+                .get(base)
+                .wrapping_add(self.registers.get(index))
+                .wrapping_add(displacement as u16),
+            _ => return Err(format!("{:?} is not a memory operand", operand).into()),
+        };
+        let segment = match self.segment_override {
+            Some(segment) => segment,
+            None => operand.default_segment().ok_or("Missing default segment")?,
+        };
+        Ok((self.segment_registers[&segment], offset))
+    }
+    /// Reads a byte or word from memory, bytes are returned in the low half of the word
+    pub fn read_memory(&self, operand: RegisterMemory, wide: bool) -> Result<u16> {
+        let (segment, offset) = self.effective_address(operand)?;
+        if wide {
+            Ok(self.memory.read_word(segment, offset))
+        } else {
+            Ok(self.memory.read_byte(segment, offset) as u16)
+        }
+    }
+    pub fn write_memory(&mut self, operand: RegisterMemory, value: u16, wide: bool) -> Result<()> {
+        let (segment, offset) = self.effective_address(operand)?;
+        if wide {
+            self.memory.write_word(segment, offset, value);
+        } else {
+            self.memory.write_byte(segment, offset, value as u8);
+        }
+        Ok(())
+    }
+    pub fn flag(&self, flag: CpuFlag) -> bool {
+        self.registers.flag(flag)
+    }
+    pub fn segment_register(&self, register: SegmentRegister) -> u16 {
+        self.segment_registers[&register]
+    }
+    pub fn set_segment_register(&mut self, register: SegmentRegister, value: u16) {
+        self.segment_registers.insert(register, value);
+    }
+    /// Set by HLT until an interrupt wakes the cpu up again
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+    /// Whether a hardware interrupt can still end a HLT, which takes an interrupt controller
+    /// and IF set
+    pub fn can_wake(&self) -> bool {
+        self.interrupt_controller.is_some() && self.flag(CpuFlag::IF)
+    }
+    pub fn halt(&mut self) {
+        self.halted = true;
+    }
+    /// Which processor the clock estimates are for, an 8086 unless set
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+    /// Clocks estimated for everything executed so far
+    pub fn clocks(&self) -> u64 {
+        self.clocks
+    }
+    /// The estimate for the instruction the last step executed
+    pub fn last_clocks(&self) -> Clocks {
+        self.last_clocks
+    }
+    /// Whether the memory `mnemonic` transfers data to or from, not counting the stack, is at
+    /// an odd address. Worked out before the instruction changes any of the address registers
+    fn odd_address(&self, mnemonic: Mnemonic) -> bool {
+        let def_use = mnemonic.def_use();
+        def_use
+            .uses
+            .iter()
+            .chain(&def_use.defs)
+            .any(|location| match *location {
+                Location::Memory(operand) => self
+                    .effective_address(operand)
+                    .is_ok_and(|(_, offset)| offset & 1 != 0),
+                Location::ImplicitMemory(SegmentRegister::SS, _) => false,
+                Location::ImplicitMemory(_, register) => self.registers.get(register) & 1 != 0,
+                _ => false,
+            })
+    }
+    fn count_clocks(&mut self, clocks: Clocks) {
+        self.clocks += clocks.total() as u64;
+        self.last_clocks = clocks;
+        self.io.tick(clocks.total());
+    }
     fn mov(&mut self, dest: RegisterMemory, source: RegisterMemory) -> Result<()> {
-        println!("{:?} {:?}", dest, source);
-        match (dest, source) {
-            (RegisterMemory::Register(register), RegisterMemory::Immediate(value)) => {
-                self.registers.entry(register).and_modify(|v| *v = value);
+        let wide = operand_width(dest, source)?;
+        let value = self.read_operand(source, wide)?;
+        self.write_operand(dest, value, wide)
+    }
+    /// Runs a two operand ALU operation on `dest` and `source`, CMP and TEST only keep the flags
+    /// so they don't `store` the result
+    fn alu(
+        &mut self,
+        dest: RegisterMemory,
+        source: RegisterMemory,
+        store: bool,
+        operation: fn(&mut RegisterFile, u16, u16, bool) -> u16,
+    ) -> Result<()> {
+        let wide = operand_width(dest, source)?;
+        let a = self.read_operand(dest, wide)?;
+        let b = self.read_operand(source, wide)?;
+        let result = operation(&mut self.registers, a, b, wide);
+        if store {
+            self.write_operand(dest, result, wide)?;
+        }
+        Ok(())
+    }
+    /// INC and DEC set the same flags as ADD and SUB except for CF, which they leave alone
+    fn inc_dec(&mut self, dest: RegisterMemory, wide: bool, increment: bool) -> Result<()> {
+        let carry = self.flag(CpuFlag::CF);
+        let value = self.read_operand(dest, wide)?;
+        let result = if increment {
+            alu::add(&mut self.registers, value, 1, wide)
+        } else {
+            alu::sub(&mut self.registers, value, 1, wide)
+        };
+        self.registers.set_flag(CpuFlag::CF, carry);
+        self.write_operand(dest, result, wide)
+    }
+    /// LDS and LES load the offset of a far pointer into `dest` and its segment into `segment`
+    fn load_far_pointer(
+        &mut self,
+        dest: RegisterMemory,
+        source: RegisterMemory,
+        segment: SegmentRegister,
+    ) -> Result<()> {
+        let (pointer_segment, offset) = self.effective_address(source)?;
+        let value = self.memory.read_word(pointer_segment, offset);
+        let far_segment = self
+            .memory
+            .read_word(pointer_segment, offset.wrapping_add(2));
+        self.write_operand(dest, value, true)?;
+        self.segment_registers.insert(segment, far_segment);
+        Ok(())
+    }
+    /// The count is either 1 or all of CL, the 8086 doesn't mask it to 5 bits like later CPUs
+    fn shift(
+        &mut self,
+        operator: LogicOperator,
+        dest: RegisterMemory,
+        count: RegisterMemory,
+        wide: bool,
+    ) -> Result<()> {
+        let count = self.read_operand(count, false)? as u8;
+        let value = self.read_operand(dest, wide)?;
+        let result = alu::shift(&mut self.registers, operator, value, count, wide);
+        self.write_operand(dest, result, wide)
+    }
+    /// AL * r/m8 goes to AX and AX * r/m16 to DX:AX
+    fn multiply(
+        &mut self,
+        dest: RegisterMemory,
+        source: RegisterMemory,
+        signed: bool,
+    ) -> Result<()> {
+        let wide = operand_width(dest, source)?;
+        let a = self.read_operand(dest, wide)?;
+        let b = self.read_operand(source, wide)?;
+        let product = alu::mul(&mut self.registers, a, b, wide, signed);
+        self.registers.set(Register::AX, product as u16);
+        if wide {
+            self.registers.set(Register::DX, (product >> 16) as u16);
+        }
+        Ok(())
+    }
+    /// AX / r/m8 leaves the quotient in AL and remainder in AH, DX:AX / r/m16 uses AX and DX.
+    /// Dividing by zero or a quotient that doesn't fit raises interrupt 0
+    fn divide(&mut self, dest: RegisterMemory, source: RegisterMemory, signed: bool) -> Result<()> {
+        let wide = operand_width(dest, source)?;
+        let divisor = self.read_operand(source, wide)?;
+        let mut dividend = self.registers.get(Register::AX) as u32;
+        if wide {
+            dividend |= (self.registers.get(Register::DX) as u32) << 16;
+        }
+        match alu::div(dividend, divisor, wide, signed) {
+            Some((quotient, remainder)) if wide => {
+                self.registers.set(Register::AX, quotient);
+                self.registers.set(Register::DX, remainder);
             }
-            (RegisterMemory::Register(dest), RegisterMemory::Register(source)) => {
-                let source_value = *self.registers.get(&source).unwrap();
-                *self.registers.entry(dest)
-                    .or_insert(0) = source_value;
+            Some((quotient, remainder)) => {
+                self.registers.set(Register::AL, quotient);
+                self.registers.set(Register::AH, remainder);
             }
-            (_, _) => ()
+            None => self.interrupt(0)?,
         }
         Ok(())
     }
+    /// Pushes FLAGS, CS and IP, clears IF and TF and continues at the handler the interrupt
+    /// vector table at 0000:0000 has for `vector`. A hooked interrupt runs its Rust handler
+    /// instead and carries on after the interrupting instruction as if it had executed IRET
+    pub fn interrupt(&mut self, vector: u8) -> Result<()> {
+        let function = (self.registers.get(Register::AX) >> 8) as u8;
+        if let Some(index) = self
+            .interrupt_hooks
+            .iter()
+            .rposition(|hook| hook.matches(vector, function))
+        {
+            // the handler borrows the whole cpu, so the hooks are moved out while it runs and
+            // any it registers itself are kept after the existing ones
+            let mut hooks = std::mem::take(&mut self.interrupt_hooks);
+            let result = (hooks[index].handler)(self);
+            hooks.append(&mut self.interrupt_hooks);
+            self.interrupt_hooks = hooks;
+            return result;
+        }
+        self.push(self.registers.flags());
+        self.registers.set_flag(CpuFlag::IF, false);
+        self.registers.set_flag(CpuFlag::TF, false);
+        let entry = vector as u16 * 4;
+        let offset = self.memory.read_word(0, entry);
+        let segment = self.memory.read_word(0, entry + 2);
+        self.call_far(segment, offset);
+        Ok(())
+    }
+    pub fn push(&mut self, value: u16) {
+        let sp = self.registers.get(Register::SP).wrapping_sub(2);
+        self.registers.set(Register::SP, sp);
+        self.memory
+            .write_word(self.segment_registers[&SegmentRegister::SS], sp, value);
+    }
+    pub fn pop(&mut self) -> u16 {
+        let sp = self.registers.get(Register::SP);
+        self.registers.set(Register::SP, sp.wrapping_add(2));
+        self.memory
+            .read_word(self.segment_registers[&SegmentRegister::SS], sp)
+    }
+    /// `RET imm16` discards `bytes` worth of arguments after popping the return address
+    fn release_stack(&mut self, bytes: Option<u16>) {
+        let sp = self.registers.get(Register::SP);
+        self.registers
+            .set(Register::SP, sp.wrapping_add(bytes.unwrap_or(0)));
+    }
+    fn call_far(&mut self, segment: u16, offset: u16) {
+        self.push(self.segment_registers[&SegmentRegister::CS]);
+        self.push(self.registers.ip);
+        self.segment_registers.insert(SegmentRegister::CS, segment);
+        self.registers.ip = offset;
+    }
+    /// Resolves the `segment:offset` an indirect CALL or JMP transfers to, a far target is an
+    /// offset followed by a segment in memory while a near one stays in the current code segment
+    fn branch_target(&self, target: RegisterMemory, far: bool) -> Result<(u16, u16)> {
+        if far {
+            let (segment, offset) = self.effective_address(target)?;
+            Ok((
+                self.memory.read_word(segment, offset.wrapping_add(2)),
+                self.memory.read_word(segment, offset),
+            ))
+        } else {
+            Ok((
+                self.segment_registers[&SegmentRegister::CS],
+                self.read_operand(target, true)?,
+            ))
+        }
+    }
+    /// Short branches are relative to the already advanced IP
+    fn jump_if(&mut self, condition: bool, label: u8) {
+        self.branch_taken = condition;
+        if condition {
+            self.registers.ip = self.registers.ip.wrapping_add(label as i8 as u16);
+        }
+    }
+    /// The signed less than condition, the result is negative unless the subtraction overflowed
+    fn less(&self) -> bool {
+        self.flag(CpuFlag::SF) != self.flag(CpuFlag::OF)
+    }
+    /// The LOOP family decrements CX without touching the flags
+    fn decrement_count(&mut self) -> u16 {
+        let count = self.registers.get(Register::CX).wrapping_sub(1);
+        self.registers.set(Register::CX, count);
+        count
+    }
+    pub fn read_operand(&self, operand: RegisterMemory, wide: bool) -> Result<u16> {
+        match operand {
+            RegisterMemory::Register(register) => Ok(self.registers.get(register)),
+            RegisterMemory::SegmentRegister(segment) => Ok(self.segment_registers[&segment]),
+            RegisterMemory::Immediate(value) => Ok(value as u16),
+            RegisterMemory::ImmediateWide(value) => Ok(value),
+            memory => self.read_memory(memory, wide),
+        }
+    }
+    pub fn write_operand(&mut self, operand: RegisterMemory, value: u16, wide: bool) -> Result<()> {
+        match operand {
+            RegisterMemory::Register(register) => {
+                self.registers.set(register, value);
+            }
+            RegisterMemory::SegmentRegister(segment) => {
+                self.segment_registers.insert(segment, value);
+            }
+            RegisterMemory::Immediate(_) | RegisterMemory::ImmediateWide(_) => {
+                return Err("Cannot write to an immediate".into())
+            }
+            memory => self.write_memory(memory, value, wide)?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    use super::{Cpu, CpuFlag};
+    use crate::clocks::Variant;
+    use crate::instructions::Mnemonic;
+    use crate::io::IoDevice;
+    use crate::pic::Pic;
+    use crate::pit::Pit;
+    use crate::registers::{Register, RegisterMemory, SegmentRegister};
+
+    fn run(binary: &[u8]) -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.load(binary);
+        while (cpu.registers.ip as usize) < binary.len() {
+            cpu.step().unwrap();
+        }
+        cpu
+    }
+
+    /// The set flags in the order the reference traces print them
+    fn flag_letters(cpu: &Cpu) -> String {
+        [
+            (CpuFlag::CF, 'C'),
+            (CpuFlag::PF, 'P'),
+            (CpuFlag::AF, 'A'),
+            (CpuFlag::ZF, 'Z'),
+            (CpuFlag::SF, 'S'),
+            (CpuFlag::TF, 'T'),
+            (CpuFlag::IF, 'I'),
+            (CpuFlag::DF, 'D'),
+            (CpuFlag::OF, 'O'),
+        ]
+        .iter()
+        .filter(|(flag, _)| cpu.flag(*flag))
+        .map(|(_, letter)| letter)
+        .collect()
+    }
+
+    const TRACED_REGISTERS: [(&str, Register); 8] = [
+        ("ax", Register::AX),
+        ("bx", Register::BX),
+        ("cx", Register::CX),
+        ("dx", Register::DX),
+        ("sp", Register::SP),
+        ("bp", Register::BP),
+        ("si", Register::SI),
+        ("di", Register::DI),
+    ];
+
+    /// Parses the value a register or the flags changed to from a reference trace line
+    fn traced<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+        let (_, change) = line.split_once(&format!(" {}:", name))?;
+        let change = change.split_whitespace().next().unwrap_or_default();
+        Some(change.split_once("->").unwrap().1)
+    }
+
+    /// The clocks a trace line adds, the running total and how the added clocks break down,
+    /// from e.g. `Clocks: +14 = 36 (8 + 6ea)`
+    fn traced_clocks(line: &str) -> Option<(&str, &str, Option<&str>)> {
+        let (_, clocks) = line.split_once("Clocks: +")?;
+        let (clocks, _) = clocks.split_once(" |").unwrap();
+        let (added, total) = clocks.split_once(" = ").unwrap();
+        let (total, breakdown) = match total.split_once(" (") {
+            Some((total, breakdown)) => (total, breakdown.strip_suffix(')')),
+            None => (total, None),
+        };
+        Some((added, total, breakdown))
+    }
+
+    /// Steps through a listing and checks IP, the flags and the clocks after every instruction
+    /// against its reference trace
+    fn run_trace(binary: &[u8], trace: &str) -> Cpu {
+        run_trace_on(Variant::I8086, binary, trace)
+    }
+
+    fn run_trace_on(variant: Variant, binary: &[u8], trace: &str) -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.set_variant(variant);
+        cpu.load(binary);
+        let mut flags = String::new();
+        for line in trace.lines().filter(|line| line.contains(" ; ")) {
+            cpu.step().unwrap();
+            if let Some(change) = traced(line, "flags") {
+                flags = change.to_string();
+            }
+            assert_eq!(flag_letters(&cpu), flags, "{}", line);
+            if let Some(ip) = traced(line, "ip") {
+                assert_eq!(format!("{:#x}", cpu.registers.ip), ip, "{}", line);
+            }
+            for (name, register) in TRACED_REGISTERS {
+                if let Some(value) = traced(line, name) {
+                    let actual = cpu.registers.get(register);
+                    assert_eq!(format!("{:#x}", actual), value, "{}", line);
+                }
+            }
+            if let Some((added, total, breakdown)) = traced_clocks(line) {
+                let clocks = cpu.last_clocks();
+                assert_eq!(clocks.total().to_string(), added, "{}", line);
+                assert_eq!(cpu.clocks().to_string(), total, "{}", line);
+                assert_eq!(clocks.breakdown().as_deref(), breakdown, "{}", line);
+            }
+        }
+        cpu
+    }
+
+    #[test]
+    fn test_effective_address_default_segment() {
+        let mut cpu = Cpu::new();
+        cpu.segment_registers.insert(SegmentRegister::SS, 0x1000);
+        cpu.segment_registers.insert(SegmentRegister::DS, 0x2000);
+        cpu.registers.set(Register::BP, 4);
+        cpu.registers.set(Register::BX, 0xfffe);
+        assert_eq!(
+            cpu.effective_address(RegisterMemory::RegisterData(Register::BP, 2))
+                .unwrap(),
+            (0x1000, 6)
+        );
+        assert_eq!(
+            cpu.effective_address(RegisterMemory::RegisterData(Register::BX, 4))
+                .unwrap(),
+            (0x2000, 2)
+        );
+        cpu.segment_override = Some(SegmentRegister::SS);
+        assert_eq!(
+            cpu.effective_address(RegisterMemory::RegisterAddress(Register::BX))
+                .unwrap(),
+            (0x1000, 0xfffe)
+        );
+    }
+
+    #[test]
+    fn test_memory_mov() {
+        let cpu = run(include_bytes!("../listings/part1/listing_0051_memory_mov"));
+        assert_eq!(cpu.registers.get(Register::BX), 1);
+        assert_eq!(cpu.registers.get(Register::CX), 2);
+        assert_eq!(cpu.registers.get(Register::DX), 10);
+        assert_eq!(cpu.registers.get(Register::BP), 4);
+        assert_eq!(cpu.memory.read_word(0, 1004), 10);
+    }
+
+    #[test]
+    fn test_draw_rectangle() {
+        let cpu = run_trace(
+            include_bytes!("../listings/part1/listing_0054_draw_rectangle"),
+            include_str!("../listings/part1/listing_0054_draw_rectangle.txt"),
+        );
+        assert_eq!(cpu.registers.get(Register::CX), 64);
+        assert_eq!(cpu.registers.get(Register::DX), 64);
+        assert_eq!(cpu.registers.get(Register::BP), 0x4100);
+        assert_eq!(cpu.registers.ip, 0x0026);
+        assert_eq!(flag_letters(&cpu), "PZ");
+        // the pixel at x 5, y 7 is red 5, blue 7 and opaque
+        let pixel = 256 + (7 * 64 + 5) * 4;
+        let bytes: Vec<u8> = (0..4).map(|i| cpu.memory.read_byte(0, pixel + i)).collect();
+        assert_eq!(bytes, [5, 0, 7, 255]);
+    }
+
+    #[test]
+    fn test_segment_register_mov() {
+        let mut cpu = Cpu::new();
+        let instructions = [
+            Mnemonic::MOV {
+                dest: RegisterMemory::Register(Register::AX),
+                source: RegisterMemory::ImmediateWide(0x1000),
+            },
+            Mnemonic::MOV {
+                dest: RegisterMemory::SegmentRegister(SegmentRegister::ES),
+                source: RegisterMemory::Register(Register::AX),
+            },
+            Mnemonic::MOV {
+                dest: RegisterMemory::Register(Register::AL),
+                source: RegisterMemory::Immediate(0x34),
+            },
+            Mnemonic::SEGMENTOVERRIDE(SegmentRegister::ES),
+            Mnemonic::MOV {
+                dest: RegisterMemory::DirectAddress(2),
+                source: RegisterMemory::Register(Register::AL),
+            },
+            Mnemonic::MOV {
+                dest: RegisterMemory::DirectAddress(4),
+                source: RegisterMemory::SegmentRegister(SegmentRegister::ES),
+            },
+            Mnemonic::MOV {
+                dest: RegisterMemory::SegmentRegister(SegmentRegister::DS),
+                source: RegisterMemory::DirectAddress(4),
+            },
+        ];
+        for instruction in instructions {
+            cpu.execute(instruction).unwrap();
+        }
+        assert_eq!(cpu.memory.read_byte(0x1000, 2), 0x34);
+        assert_eq!(cpu.memory.read_byte(0, 2), 0x00);
+        assert_eq!(cpu.memory.read_word(0, 4), 0x1000);
+        assert_eq!(cpu.segment_register(SegmentRegister::DS), 0x1000);
+    }
+
+    #[test]
+    fn test_byte_register_mov() {
+        let cpu = run(include_bytes!("../listings/part1/listing_0045_challenge_register_movs"));
+        assert_eq!(cpu.registers.get(Register::AX), 0x4411);
+        assert_eq!(cpu.registers.get(Register::BX), 0x3344);
+        assert_eq!(cpu.registers.get(Register::CX), 0x6677);
+        assert_eq!(cpu.registers.get(Register::DX), 0x7788);
+        assert_eq!(cpu.segment_register(SegmentRegister::ES), 0x6677);
+        assert_eq!(cpu.segment_register(SegmentRegister::SS), 0x4411);
+        assert_eq!(cpu.segment_register(SegmentRegister::DS), 0x3344);
+    }
+
+    #[test]
+    fn test_add_sub_cmp() {
+        let cpu = run_trace(
+            include_bytes!("../listings/part1/listing_0046_add_sub_cmp"),
+            include_str!("../listings/part1/listing_0046_add_sub_cmp.txt"),
+        );
+        assert_eq!(cpu.registers.get(Register::BX), 0xe102);
+        assert_eq!(cpu.registers.get(Register::CX), 0x0f01);
+        assert_eq!(cpu.registers.get(Register::SP), 0x03e6);
+    }
+
+    #[test]
+    fn test_challenge_flags() {
+        let cpu = run_trace(
+            include_bytes!("../listings/part1/listing_0047_challenge_flags"),
+            include_str!("../listings/part1/listing_0047_challenge_flags.txt"),
+        );
+        assert_eq!(cpu.registers.get(Register::BX), 0x9ca5);
+        assert_eq!(cpu.registers.get(Register::DX), 0x000a);
+        assert_eq!(cpu.registers.get(Register::SP), 0x0063);
+        assert_eq!(cpu.registers.get(Register::BP), 0x0062);
+        assert_eq!(flag_letters(&cpu), "CPAS");
+    }
+
+    #[test]
+    fn test_logic_and_unary() {
+        // mov al, 0xf0; test al, 0x0f; not al; neg al; dec cx
+        let cpu = run(&[0xb0, 0xf0, 0xa8, 0x0f, 0xf6, 0xd0, 0xf6, 0xd8, 0x49]);
+        assert_eq!(cpu.registers.get(Register::AL), 0xf1);
+        assert_eq!(cpu.registers.get(Register::CX), 0xffff);
+        assert!(cpu.flag(CpuFlag::CF));
+        assert!(cpu.flag(CpuFlag::SF));
+    }
+
+    #[test]
+    fn test_ip_register() {
+        let cpu = run_trace(
+            include_bytes!("../listings/part1/listing_0048_ip_register"),
+            include_str!("../listings/part1/listing_0048_ip_register.txt"),
+        );
+        assert_eq!(cpu.registers.ip, 0x000e);
+        assert_eq!(cpu.registers.get(Register::CX), 0xfce0);
+        assert_eq!(flag_letters(&cpu), "CS");
+    }
+
+    #[test]
+    fn test_step_wraps_ip() {
+        let mut cpu = Cpu::new();
+        cpu.registers.ip = 0xfffe;
+        // mov ax, 0x1234 straddling the end of the code segment
+        cpu.load(&[0xb8, 0x34]);
+        cpu.memory.write_byte(0, 0, 0x12);
+        let instruction = cpu.step().unwrap();
+        assert_eq!(instruction.offset, 0xfffe);
+        assert_eq!(instruction.length, 3);
+        assert_eq!(cpu.registers.ip, 0x0001);
+        assert_eq!(cpu.registers.get(Register::AX), 0x1234);
+    }
+
+    #[test]
+    fn test_conditional_jumps() {
+        let cpu = run_trace(
+            include_bytes!("../listings/part1/listing_0049_conditional_jumps"),
+            include_str!("../listings/part1/listing_0049_conditional_jumps.txt"),
+        );
+        assert_eq!(cpu.registers.get(Register::BX), 0x0406);
+        assert_eq!(cpu.registers.ip, 0x000e);
+        assert_eq!(flag_letters(&cpu), "PZ");
+    }
+
+    #[test]
+    fn test_challenge_jumps() {
+        let cpu = run_trace(
+            include_bytes!("../listings/part1/listing_0050_challenge_jumps"),
+            include_str!("../listings/part1/listing_0050_challenge_jumps.txt"),
+        );
+        assert_eq!(cpu.registers.get(Register::AX), 0x000d);
+        assert_eq!(cpu.registers.get(Register::BX), 0xfffb);
+        assert_eq!(cpu.registers.ip, 0x001c);
+        assert_eq!(flag_letters(&cpu), "CAS");
+    }
+
+    #[test]
+    fn test_memory_add_loop() {
+        let cpu = run_trace(
+            include_bytes!("../listings/part1/listing_0052_memory_add_loop"),
+            include_str!("../listings/part1/listing_0052_memory_add_loop.txt"),
+        );
+        assert_eq!(cpu.registers.get(Register::BX), 6);
+        assert_eq!(cpu.registers.get(Register::CX), 4);
+        assert_eq!(cpu.registers.get(Register::SI), 6);
+        assert_eq!(cpu.registers.ip, 0x0023);
+        assert_eq!(flag_letters(&cpu), "PZ");
+    }
+
+    #[test]
+    fn test_signed_conditions() {
+        // mov ax, -1; cmp ax, 1; jl $+5; mov dx, 1; mov cx, 2; loop $; jcxz $+5; mov si, 1
+        let cpu = run(&[
+            0xb8, 0xff, 0xff, 0x83, 0xf8, 0x01, 0x7c, 0x03, 0xba, 0x01, 0x00, 0xb9, 0x02, 0x00,
+            0xe2, 0xfe, 0xe3, 0x03, 0xbe, 0x01, 0x00,
+        ]);
+        assert_eq!(cpu.registers.get(Register::DX), 0);
+        assert_eq!(cpu.registers.get(Register::CX), 0);
+        assert_eq!(cpu.registers.get(Register::SI), 0);
+        assert_eq!(cpu.registers.ip, 21);
+    }
+
+    #[test]
+    fn test_stack() {
+        // mov sp, 0x100; mov bx, 7; push bx; push sp; pushf; stc; popf; pop cx;
+        // push word [0x200]; call $+6; pop dx; jmp $+5; ret 2
+        let cpu = run(&[
+            0xbc, 0x00, 0x01, 0xbb, 0x07, 0x00, 0x53, 0x54, 0x9c, 0xf9, 0x9d, 0x59, 0xff, 0x36,
+            0x00, 0x02, 0xe8, 0x03, 0x00, 0x5a, 0xeb, 0x03, 0xc2, 0x02, 0x00,
+        ]);
+        assert!(!cpu.flag(CpuFlag::CF));
+        assert_eq!(cpu.registers.get(Register::CX), 0x00fc);
+        assert_eq!(cpu.registers.get(Register::DX), 0x0007);
+        assert_eq!(cpu.registers.get(Register::SP), 0x0100);
+        assert_eq!(cpu.registers.ip, 0x0019);
+        assert_eq!(cpu.memory.read_word(0, 0x00fa), 0x0013);
+    }
+
+    #[test]
+    fn test_far_call() {
+        let mut cpu = Cpu::new();
+        cpu.registers.set(Register::SP, 0x100);
+        cpu.memory.write_word(0, 0x200, 0x0010);
+        cpu.memory.write_word(0, 0x202, 0x1000);
+        // call far [0x200] at 0000:0000, retf 4 at 1000:0010
+        cpu.load(&[0xff, 0x1e, 0x00, 0x02]);
+        cpu.memory.load(0x1000, 0x0010, &[0xca, 0x04, 0x00]);
+        cpu.step().unwrap();
+        assert_eq!(cpu.segment_register(SegmentRegister::CS), 0x1000);
+        assert_eq!(cpu.registers.ip, 0x0010);
+        assert_eq!(cpu.memory.read_word(0, 0xfc), 0x0004);
+        cpu.step().unwrap();
+        assert_eq!(cpu.segment_register(SegmentRegister::CS), 0);
+        assert_eq!(cpu.registers.ip, 0x0004);
+        assert_eq!(cpu.registers.get(Register::SP), 0x0104);
+    }
+
+    #[test]
+    fn test_multiply_divide() {
+        // mov ax, 300; mov bx, 7; mul bx; mov cl, 10; div cl; mov cl, -3; imul cl
+        let cpu = run(&[
+            0xb8, 0x2c, 0x01, 0xbb, 0x07, 0x00, 0xf7, 0xe3, 0xb1, 0x0a, 0xf6, 0xf1, 0xb1, 0xfd,
+            0xf6, 0xe9,
+        ]);
+        assert_eq!(cpu.registers.get(Register::AX), 0x008a);
+        assert_eq!(cpu.registers.get(Register::DX), 0);
+        assert!(cpu.flag(CpuFlag::CF));
+        assert!(cpu.flag(CpuFlag::OF));
+    }
+
+    #[test]
+    fn test_divide_error() {
+        let mut cpu = Cpu::new();
+        cpu.registers.set(Register::SP, 0x100);
+        cpu.registers.set_flag(CpuFlag::IF, true);
+        cpu.memory.write_word(0, 0, 0x0020);
+        cpu.memory.write_word(0, 2, 0x2000);
+        cpu.segment_registers.insert(SegmentRegister::CS, 0x1000);
+        // div cl with cl = 0
+        cpu.load(&[0xf6, 0xf1]);
+        cpu.step().unwrap();
+        assert_eq!(cpu.segment_register(SegmentRegister::CS), 0x2000);
+        assert_eq!(cpu.registers.ip, 0x0020);
+        assert!(!cpu.flag(CpuFlag::IF));
+        assert_eq!(cpu.pop(), 0x0002);
+        assert_eq!(cpu.pop(), 0x1000);
+        assert_eq!(cpu.pop() & CpuFlag::IF.mask(), CpuFlag::IF.mask());
+        // aam with a zero base
+        cpu.load(&[0xd4, 0x00]);
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.ip, 0x0020);
+        assert_eq!(cpu.pop(), 0x0022);
+    }
+
+    #[test]
+    fn test_shift_count() {
+        // mov bx, 0x8001; mov cl, 33; shl bx, cl; mov byte [256], 0x81; ror byte [256], 1
+        let cpu = run(&[
+            0xbb, 0x01, 0x80, 0xb1, 0x21, 0xd3, 0xe3, 0xc6, 0x06, 0x00, 0x01, 0x81, 0xd0, 0x0e,
+            0x00, 0x01,
+        ]);
+        assert_eq!(cpu.registers.get(Register::BX), 0);
+        assert_eq!(cpu.memory.read_word(0, 256), 0x00c0);
+        assert!(cpu.flag(CpuFlag::CF));
+        assert!(cpu.flag(CpuFlag::ZF));
+    }
+
+    #[test]
+    fn test_flag_and_accumulator_utilities() {
+        // stc; cmc; std; sti; lahf; mov al, 0x80; cbw; cwd; mov ah, 0xd5; sahf
+        let cpu = run(&[
+            0xf9, 0xf5, 0xfd, 0xfb, 0x9f, 0xb0, 0x80, 0x98, 0x99, 0xb4, 0xd5, 0x9e,
+        ]);
+        assert_eq!(cpu.registers.get(Register::AX), 0xd580);
+        assert_eq!(cpu.registers.get(Register::DX), 0xffff);
+        assert_eq!(flag_letters(&cpu), "CPAZSID");
+    }
+
+    #[test]
+    fn test_xlat() {
+        let mut cpu = Cpu::new();
+        cpu.segment_registers.insert(SegmentRegister::DS, 0x1000);
+        cpu.segment_registers.insert(SegmentRegister::ES, 0x2000);
+        cpu.memory.write_byte(0x1000, 0x0105, 0xaa);
+        cpu.memory.write_byte(0x2000, 0x0105, 0xbb);
+        cpu.registers.set(Register::BX, 0x0100);
+        cpu.registers.set(Register::AL, 5);
+        cpu.execute(Mnemonic::XLAT).unwrap();
+        assert_eq!(cpu.registers.get(Register::AL), 0xaa);
+        cpu.registers.set(Register::AL, 5);
+        cpu.execute(Mnemonic::SEGMENTOVERRIDE(SegmentRegister::ES))
+            .unwrap();
+        cpu.execute(Mnemonic::XLAT).unwrap();
+        assert_eq!(cpu.registers.get(Register::AL), 0xbb);
+    }
+
+    #[test]
+    fn test_load_effective_address() {
+        // mov bx, 0x200; mov si, 4; lea ax, [bx+si+0x10]; mov word [0x200], 0x1234;
+        // mov word [0x202], 0x5678; les di, [bx]; lds cx, [bx]
+        let cpu = run(&[
+            0xbb, 0x00, 0x02, 0xbe, 0x04, 0x00, 0x8d, 0x40, 0x10, 0xc7, 0x06, 0x00, 0x02, 0x34,
+            0x12, 0xc7, 0x06, 0x02, 0x02, 0x78, 0x56, 0xc4, 0x3f, 0xc5, 0x0f,
+        ]);
+        assert_eq!(cpu.registers.get(Register::AX), 0x0214);
+        assert_eq!(cpu.registers.get(Register::DI), 0x1234);
+        assert_eq!(cpu.registers.get(Register::CX), 0x1234);
+        assert_eq!(cpu.segment_register(SegmentRegister::ES), 0x5678);
+        assert_eq!(cpu.segment_register(SegmentRegister::DS), 0x5678);
+    }
+
+    #[test]
+    fn test_xchg() {
+        // mov ax, 1; mov dx, 2; xchg ax, dx; mov byte [0x100], 7; xchg [0x100], dl
+        let cpu = run(&[
+            0xb8, 0x01, 0x00, 0xba, 0x02, 0x00, 0x92, 0xc6, 0x06, 0x00, 0x01, 0x07, 0x86, 0x16,
+            0x00, 0x01,
+        ]);
+        assert_eq!(cpu.registers.get(Register::AX), 2);
+        assert_eq!(cpu.registers.get(Register::DX), 7);
+        assert_eq!(cpu.memory.read_byte(0, 0x100), 1);
+    }
+
+    #[test]
+    fn test_interrupts() {
+        let mut cpu = Cpu::new();
+        cpu.registers.set(Register::SP, 0x100);
+        // INT 3, INTO and INT 21h all share a handler at 0100:0000 that counts calls in BX
+        for vector in [3, 4, 0x21] {
+            cpu.memory.write_word(0, vector * 4, 0x0000);
+            cpu.memory.write_word(0, vector * 4 + 2, 0x0100);
+        }
+        // inc bx; iret
+        cpu.memory.load(0x0100, 0, &[0x43, 0xcf]);
+        cpu.segment_registers.insert(SegmentRegister::CS, 0x0200);
+        // sti; int3; into; mov al, 0x7f; add al, 1; into; int 21h
+        let program = [0xfb, 0xcc, 0xce, 0xb0, 0x7f, 0x04, 0x01, 0xce, 0xcd, 0x21];
+        cpu.load(&program);
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.segment_register(SegmentRegister::CS), 0x0100);
+        assert!(!cpu.flag(CpuFlag::IF));
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.segment_register(SegmentRegister::CS), 0x0200);
+        assert_eq!(cpu.registers.ip, 0x0002);
+        assert!(cpu.flag(CpuFlag::IF));
+        while cpu.registers.ip as usize != program.len() {
+            cpu.step().unwrap();
+        }
+        assert_eq!(cpu.registers.get(Register::BX), 3);
+        assert_eq!(cpu.registers.get(Register::SP), 0x100);
+        assert!(cpu.flag(CpuFlag::OF));
+    }
+
+    #[test]
+    fn test_interrupt_hooks() {
+        let mut cpu = Cpu::new();
+        cpu.registers.set(Register::SP, 0x100);
+        // the table entry for 10h points at 0100:0000, which counts unhooked calls in BX
+        cpu.memory.write_word(0, 0x40, 0x0000);
+        cpu.memory.write_word(0, 0x42, 0x0100);
+        // inc bx; iret
+        cpu.memory.load(0x0100, 0, &[0x43, 0xcf]);
+        cpu.segment_registers.insert(SegmentRegister::CS, 0x0200);
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let teletype = Rc::clone(&output);
+        cpu.hook_interrupt(0x10, Some(0x0e), move |cpu| {
+            teletype
+                .borrow_mut()
+                .push(cpu.registers.get(Register::AL) as u8);
+            Ok(())
+        });
+        cpu.hook_interrupt(0x21, None, |cpu| {
+            cpu.memory.write_byte(0, 0x300, 0x2a);
+            cpu.registers.set_flag(CpuFlag::CF, true);
+            Ok(())
+        });
+        // mov ax, 0x0e48; int 10h; mov al, 0x69; int 10h; mov ah, 0; int 10h; int 21h
+        let program = [
+            0xb8, 0x48, 0x0e, 0xcd, 0x10, 0xb0, 0x69, 0xcd, 0x10, 0xb4, 0x00, 0xcd, 0x10, 0xcd,
+            0x21,
+        ];
+        cpu.load(&program);
+        while cpu.registers.ip as usize != program.len() {
+            cpu.step().unwrap();
+        }
+        assert_eq!(output.borrow().as_slice(), b"Hi");
+        assert_eq!(cpu.registers.get(Register::BX), 1);
+        assert_eq!(cpu.memory.read_byte(0, 0x300), 0x2a);
+        assert!(cpu.flag(CpuFlag::CF));
+        assert_eq!(cpu.registers.get(Register::SP), 0x100);
+
+        cpu.hook_interrupt(0x21, None, |_| Err("unsupported".into()));
+        cpu.registers.ip = 0;
+        cpu.load(&[0xcd, 0x21]);
+        assert!(cpu.step().is_err());
+    }
+
+    /// Ports that read back the last value written to them
+    #[derive(Default)]
+    struct Latches(HashMap<u16, u8>);
+
+    impl IoDevice for Latches {
+        fn read_byte(&mut self, port: u16) -> u8 {
+            self.0.get(&port).copied().unwrap_or(0)
+        }
+        fn write_byte(&mut self, port: u16, value: u8) {
+            self.0.insert(port, value);
+        }
+    }
+
+    #[test]
+    fn test_port_io() {
+        let mut cpu = Cpu::new();
+        let latches = Rc::new(RefCell::new(Latches::default()));
+        cpu.io.attach(0x0040..=0x0043, Rc::clone(&latches));
+        cpu.io.attach(0x03f8..=0x03ff, Rc::clone(&latches));
+        // mov dx, 0x3f8; mov ax, 0x1234; out 0x42, ax; mov al, 0x41; out dx, al; in al, 0x43;
+        // in ax, dx
+        let program = [
+            0xba, 0xf8, 0x03, 0xb8, 0x34, 0x12, 0xe7, 0x42, 0xb0, 0x41, 0xee, 0xe4, 0x43, 0xed,
+        ];
+        cpu.load(&program);
+        while (cpu.registers.ip as usize) < program.len() {
+            cpu.step().unwrap();
+        }
+        assert_eq!(latches.borrow().0[&0x42], 0x34);
+        assert_eq!(latches.borrow().0[&0x43], 0x12);
+        assert_eq!(latches.borrow().0[&0x3f8], 0x41);
+        // IN AX, DX reads 0x3f8 and the never written 0x3f9
+        assert_eq!(cpu.registers.get(Register::AX), 0x0041);
+    }
+
+    #[test]
+    fn test_timer_interrupts() {
+        let mut cpu = Cpu::new();
+        let pic = Pic::attach(&mut cpu);
+        Pit::attach(&mut cpu, Rc::clone(&pic));
+        cpu.registers.set(Register::SP, 0x100);
+        cpu.memory.write_word(0, 0x08 * 4, 0x0000);
+        cpu.memory.write_word(0, 0x08 * 4 + 2, 0x0100);
+        // inc bx; mov al, 0x20; out 0x20, al; iret
+        let handler = [0x43, 0xb0, 0x20, 0xe6, 0x20, 0xcf];
+        cpu.memory.load(0x0100, 0, &handler);
+        cpu.segment_registers.insert(SegmentRegister::CS, 0x0200);
+        // mov al, 0x34; out 0x43, al; mov al, 200; out 0x40, al; mov al, 0; out 0x40, al;
+        // sti; hlt; hlt; cli
+        let program = [
+            0xb0, 0x34, 0xe6, 0x43, 0xb0, 0xc8, 0xe6, 0x40, 0xb0, 0x00, 0xe6, 0x40, 0xfb, 0xf4,
+            0xf4, 0xfa,
+        ];
+        cpu.load(&program);
+        let mut steps = 0;
+        while cpu.registers.ip as usize != program.len() {
+            cpu.step().unwrap();
+            steps += 1;
+            assert!(steps < 1000);
+        }
+        // each HLT waited for one of the 800 clock timer periods
+        assert_eq!(cpu.registers.get(Register::BX), 2);
+        assert_eq!(cpu.segment_register(SegmentRegister::CS), 0x0200);
+        assert!(!cpu.flag(CpuFlag::IF));
+        assert!(!cpu.halted());
+        assert!(steps > 80);
+        // with IF clear the next tick stays pending in the controller
+        cpu.io.tick(800);
+        cpu.step().unwrap();
+        assert!(pic.borrow().pending());
+    }
+
+    #[test]
+    fn test_single_step() {
+        let mut cpu = Cpu::new();
+        cpu.registers.set(Register::SP, 0x100);
+        cpu.memory.write_word(0, 0x04, 0x0000);
+        cpu.memory.write_word(0, 0x06, 0x0100);
+        // inc bx; iret
+        cpu.memory.load(0x0100, 0, &[0x43, 0xcf]);
+        cpu.segment_registers.insert(SegmentRegister::CS, 0x0200);
+        // pushf; pop ax; or ah, 1; push ax; popf; nop; es: nop; pushf; pop ax;
+        // and ah, 0xfe; push ax; popf; nop
+        let program = [
+            0x9c, 0x58, 0x80, 0xcc, 0x01, 0x50, 0x9d, 0x90, 0x26, 0x90, 0x9c, 0x58, 0x80, 0xe4,
+            0xfe, 0x50, 0x9d, 0x90,
+        ];
+        cpu.load(&program);
+        for _ in 0..5 {
+            cpu.step().unwrap();
+        }
+        // the POPF that set TF isn't trapped itself
+        assert!(cpu.flag(CpuFlag::TF));
+        assert_eq!(cpu.segment_register(SegmentRegister::CS), 0x0200);
+        cpu.step().unwrap();
+        assert_eq!(cpu.segment_register(SegmentRegister::CS), 0x0100);
+        assert_eq!(cpu.registers.ip, 0x0000);
+        assert!(!cpu.flag(CpuFlag::TF));
+        // the handler isn't traced and the IRET back doesn't trap either
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.ip, 0x0008);
+        assert!(cpu.flag(CpuFlag::TF));
+        while cpu.registers.ip as usize != program.len() {
+            cpu.step().unwrap();
+        }
+        // NOP, the prefixed NOP, PUSHF, POP, AND, PUSH and the POPF clearing TF all trapped
+        assert_eq!(cpu.registers.get(Register::BX), 7);
+        assert!(!cpu.flag(CpuFlag::TF));
+        assert_eq!(cpu.registers.get(Register::SP), 0x100);
+    }
+
+    /// The reference traces time the listing as an 8086 first and then as an 8088
+    fn split_variants(trace: &str) -> (&str, &str) {
+        trace.split_once("**** 8088 ****").unwrap()
+    }
+
+    #[test]
+    fn test_estimating_cycles() {
+        let binary = include_bytes!("../listings/part1/listing_0056_estimating_cycles");
+        let (trace_8086, trace_8088) = split_variants(include_str!(
+            "../listings/part1/listing_0056_estimating_cycles.txt"
+        ));
+        let cpu = run_trace_on(Variant::I8086, binary, trace_8086);
+        assert_eq!(cpu.clocks(), 192);
+        // every word the 8088 moves takes an extra 4 clocks
+        let cpu = run_trace_on(Variant::I8088, binary, trace_8088);
+        assert_eq!(cpu.clocks(), 236);
+    }
+
+    #[test]
+    fn test_challenge_cycles() {
+        let binary = include_bytes!("../listings/part1/listing_0057_challenge_cycles");
+        let (trace_8086, trace_8088) = split_variants(include_str!(
+            "../listings/part1/listing_0057_challenge_cycles.txt"
+        ));
+        // the 8086 only pays for the words at odd addresses
+        let cpu = run_trace_on(Variant::I8086, binary, trace_8086);
+        assert_eq!(cpu.clocks(), 289);
+        run_trace_on(Variant::I8088, binary, trace_8088);
+    }
 }
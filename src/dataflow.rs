@@ -0,0 +1,541 @@
+use crate::cpu::CpuFlag;
+use crate::instructions::Mnemonic;
+use crate::registers::{Register, RegisterMemory, SegmentRegister};
+
+/// Something an instruction can read from or write to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Location {
+    Register(Register),
+    SegmentRegister(SegmentRegister),
+    Flag(CpuFlag),
+    Memory(RegisterMemory),
+    /// Memory addressed implicitly through a segment and register pair, e.g. the stack at SS:SP
+    /// or the string operands at DS:SI and ES:DI
+    ImplicitMemory(SegmentRegister, Register),
+}
+
+const ARITHMETIC_FLAGS: [CpuFlag; 6] = [
+    CpuFlag::OF,
+    CpuFlag::SF,
+    CpuFlag::ZF,
+    CpuFlag::AF,
+    CpuFlag::PF,
+    CpuFlag::CF,
+];
+
+const ALL_FLAGS: [CpuFlag; 9] = [
+    CpuFlag::OF,
+    CpuFlag::DF,
+    CpuFlag::IF,
+    CpuFlag::TF,
+    CpuFlag::SF,
+    CpuFlag::ZF,
+    CpuFlag::AF,
+    CpuFlag::PF,
+    CpuFlag::CF,
+];
+
+const STACK: Location = Location::ImplicitMemory(SegmentRegister::SS, Register::SP);
+const STRING_SOURCE: Location = Location::ImplicitMemory(SegmentRegister::DS, Register::SI);
+const STRING_DEST: Location = Location::ImplicitMemory(SegmentRegister::ES, Register::DI);
+
+/// The locations an instruction reads (`uses`) and writes (`defs`).
+///
+/// Flags an instruction leaves undefined are reported as defs since their previous value is
+/// lost. Memory operands also use the registers and segment register that address them.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DefUse {
+    pub uses: Vec<Location>,
+    pub defs: Vec<Location>,
+}
+
+impl DefUse {
+    fn use_location(&mut self, location: Location) {
+        if !self.uses.contains(&location) {
+            self.uses.push(location);
+        }
+    }
+
+    fn def_location(&mut self, location: Location) {
+        if !self.defs.contains(&location) {
+            self.defs.push(location);
+        }
+    }
+
+    fn address(&mut self, operand: RegisterMemory) {
+        for register in operand.address_registers() {
+            self.use_location(Location::Register(register));
+        }
+        if let Some(segment) = operand.default_segment() {
+            self.use_location(Location::SegmentRegister(segment));
+        }
+    }
+
+    fn read(&mut self, operand: RegisterMemory) {
+        match operand {
+            RegisterMemory::Register(register) => self.use_location(Location::Register(register)),
+            RegisterMemory::SegmentRegister(segment) => {
+                self.use_location(Location::SegmentRegister(segment))
+            }
+            RegisterMemory::Immediate(_) | RegisterMemory::ImmediateWide(_) => (),
+            memory => {
+                self.address(memory);
+                self.use_location(Location::Memory(memory));
+            }
+        }
+    }
+
+    fn write(&mut self, operand: RegisterMemory) {
+        match operand {
+            RegisterMemory::Register(register) => self.def_location(Location::Register(register)),
+            RegisterMemory::SegmentRegister(segment) => {
+                self.def_location(Location::SegmentRegister(segment))
+            }
+            RegisterMemory::Immediate(_) | RegisterMemory::ImmediateWide(_) => (),
+            memory => {
+                self.address(memory);
+                self.def_location(Location::Memory(memory));
+            }
+        }
+    }
+
+    fn use_registers(&mut self, registers: &[Register]) {
+        for register in registers {
+            self.use_location(Location::Register(*register));
+        }
+    }
+
+    fn def_registers(&mut self, registers: &[Register]) {
+        for register in registers {
+            self.def_location(Location::Register(*register));
+        }
+    }
+
+    fn use_flags(&mut self, flags: &[CpuFlag]) {
+        for flag in flags {
+            self.use_location(Location::Flag(*flag));
+        }
+    }
+
+    fn def_flags(&mut self, flags: &[CpuFlag]) {
+        for flag in flags {
+            self.def_location(Location::Flag(*flag));
+        }
+    }
+
+    fn push(&mut self) {
+        self.use_registers(&[Register::SP]);
+        self.use_location(Location::SegmentRegister(SegmentRegister::SS));
+        self.def_registers(&[Register::SP]);
+        self.def_location(STACK);
+    }
+
+    fn pop(&mut self) {
+        self.use_registers(&[Register::SP]);
+        self.use_location(Location::SegmentRegister(SegmentRegister::SS));
+        self.use_location(STACK);
+        self.def_registers(&[Register::SP]);
+    }
+
+    fn string_source(&mut self) {
+        self.use_registers(&[Register::SI]);
+        self.use_location(Location::SegmentRegister(SegmentRegister::DS));
+        self.use_flags(&[CpuFlag::DF]);
+        self.use_location(STRING_SOURCE);
+        self.def_registers(&[Register::SI]);
+    }
+
+    fn string_dest(&mut self) {
+        self.use_registers(&[Register::DI]);
+        self.use_location(Location::SegmentRegister(SegmentRegister::ES));
+        self.use_flags(&[CpuFlag::DF]);
+        self.def_registers(&[Register::DI]);
+    }
+}
+
+fn accumulator(wide: bool) -> Register {
+    if wide {
+        Register::AX
+    } else {
+        Register::AL
+    }
+}
+
+impl Mnemonic {
+    /// Every register, segment register, flag and memory operand this instruction reads and
+    /// writes, including implicit operands like DX:AX for MUL or CX for LOOP
+    pub fn def_use(&self) -> DefUse {
+        let mut def_use = DefUse::default();
+        match *self {
+            Mnemonic::MOV { dest, source } => {
+                def_use.read(source);
+                def_use.write(dest);
+            }
+            Mnemonic::ADD { dest, source }
+            | Mnemonic::SUB { dest, source }
+            | Mnemonic::AND { dest, source }
+            | Mnemonic::OR { dest, source }
+            | Mnemonic::XOR { dest, source } => {
+                def_use.read(dest);
+                def_use.read(source);
+                def_use.write(dest);
+                def_use.def_flags(&ARITHMETIC_FLAGS);
+            }
+            Mnemonic::ADC { dest, source } | Mnemonic::SBB { dest, source } => {
+                def_use.read(dest);
+                def_use.read(source);
+                def_use.use_flags(&[CpuFlag::CF]);
+                def_use.write(dest);
+                def_use.def_flags(&ARITHMETIC_FLAGS);
+            }
+            Mnemonic::CMP { dest, source } | Mnemonic::TEST { dest, source } => {
+                def_use.read(dest);
+                def_use.read(source);
+                def_use.def_flags(&ARITHMETIC_FLAGS);
+            }
+            Mnemonic::NOT { dest, .. } => {
+                def_use.read(dest);
+                def_use.write(dest);
+            }
+            Mnemonic::NEG { dest, .. } => {
+                def_use.read(dest);
+                def_use.write(dest);
+                def_use.def_flags(&ARITHMETIC_FLAGS);
+            }
+            Mnemonic::INC { dest, .. } | Mnemonic::DEC { dest, .. } => {
+                def_use.read(dest);
+                def_use.write(dest);
+                def_use.def_flags(&[
+                    CpuFlag::OF,
+                    CpuFlag::SF,
+                    CpuFlag::ZF,
+                    CpuFlag::AF,
+                    CpuFlag::PF,
+                ]);
+            }
+            // the destination of the multiply and divide group is the implicit accumulator
+            Mnemonic::MUL { dest, source }
+            | Mnemonic::IMUL { dest, source }
+            | Mnemonic::DIV { dest, source }
+            | Mnemonic::IDIV { dest, source } => {
+                let divide = matches!(self, Mnemonic::DIV { .. } | Mnemonic::IDIV { .. });
+                def_use.read(source);
+                if dest.is_wide() == Some(true) {
+                    def_use.use_registers(&[Register::AX]);
+                    if divide {
+                        def_use.use_registers(&[Register::DX]);
+                    }
+                    def_use.def_registers(&[Register::AX, Register::DX]);
+                } else if divide {
+                    def_use.use_registers(&[Register::AX]);
+                    def_use.def_registers(&[Register::AL, Register::AH]);
+                } else {
+                    def_use.use_registers(&[Register::AL]);
+                    def_use.def_registers(&[Register::AX]);
+                }
+                def_use.def_flags(&ARITHMETIC_FLAGS);
+            }
+            Mnemonic::ROL { dest, source, .. } | Mnemonic::ROR { dest, source, .. } => {
+                def_use.read(dest);
+                def_use.read(source);
+                def_use.write(dest);
+                def_use.def_flags(&[CpuFlag::CF, CpuFlag::OF]);
+            }
+            Mnemonic::RCL { dest, source, .. } | Mnemonic::RCR { dest, source, .. } => {
+                def_use.read(dest);
+                def_use.read(source);
+                def_use.use_flags(&[CpuFlag::CF]);
+                def_use.write(dest);
+                def_use.def_flags(&[CpuFlag::CF, CpuFlag::OF]);
+            }
+            Mnemonic::SAL { dest, source, .. }
+            | Mnemonic::SHR { dest, source, .. }
+            | Mnemonic::SAR { dest, source, .. } => {
+                def_use.read(dest);
+                def_use.read(source);
+                def_use.write(dest);
+                def_use.def_flags(&ARITHMETIC_FLAGS);
+            }
+            Mnemonic::XCHG { dest, source } => {
+                def_use.read(dest);
+                def_use.read(source);
+                def_use.write(dest);
+                def_use.write(source);
+            }
+            Mnemonic::LEA { dest, source } => {
+                for register in source.address_registers() {
+                    def_use.use_location(Location::Register(register));
+                }
+                def_use.write(dest);
+            }
+            Mnemonic::LDS { dest, source } | Mnemonic::LES { dest, source } => {
+                let segment = if matches!(self, Mnemonic::LDS { .. }) {
+                    SegmentRegister::DS
+                } else {
+                    SegmentRegister::ES
+                };
+                def_use.read(source);
+                def_use.write(dest);
+                def_use.def_location(Location::SegmentRegister(segment));
+            }
+            Mnemonic::DAA | Mnemonic::DAS => {
+                def_use.use_registers(&[Register::AL]);
+                def_use.use_flags(&[CpuFlag::AF, CpuFlag::CF]);
+                def_use.def_registers(&[Register::AL]);
+                def_use.def_flags(&ARITHMETIC_FLAGS);
+            }
+            Mnemonic::AAA | Mnemonic::AAS => {
+                def_use.use_registers(&[Register::AL, Register::AH]);
+                def_use.use_flags(&[CpuFlag::AF]);
+                def_use.def_registers(&[Register::AL, Register::AH]);
+                def_use.def_flags(&ARITHMETIC_FLAGS);
+            }
+            Mnemonic::AAM { .. } => {
+                def_use.use_registers(&[Register::AL]);
+                def_use.def_registers(&[Register::AL, Register::AH]);
+                def_use.def_flags(&ARITHMETIC_FLAGS);
+            }
+            Mnemonic::AAD { .. } => {
+                def_use.use_registers(&[Register::AL, Register::AH]);
+                def_use.def_registers(&[Register::AL, Register::AH]);
+                def_use.def_flags(&ARITHMETIC_FLAGS);
+            }
+            Mnemonic::CBW => {
+                def_use.use_registers(&[Register::AL]);
+                def_use.def_registers(&[Register::AH]);
+            }
+            Mnemonic::CWD => {
+                def_use.use_registers(&[Register::AX]);
+                def_use.def_registers(&[Register::DX]);
+            }
+            Mnemonic::LAHF => {
+                def_use.use_flags(&[
+                    CpuFlag::SF,
+                    CpuFlag::ZF,
+                    CpuFlag::AF,
+                    CpuFlag::PF,
+                    CpuFlag::CF,
+                ]);
+                def_use.def_registers(&[Register::AH]);
+            }
+            Mnemonic::SAHF => {
+                def_use.use_registers(&[Register::AH]);
+                def_use.def_flags(&[
+                    CpuFlag::SF,
+                    CpuFlag::ZF,
+                    CpuFlag::AF,
+                    CpuFlag::PF,
+                    CpuFlag::CF,
+                ]);
+            }
+            Mnemonic::XLAT => {
+                def_use.use_registers(&[Register::AL, Register::BX]);
+                def_use.use_location(Location::SegmentRegister(SegmentRegister::DS));
+                def_use.use_location(Location::ImplicitMemory(
+                    SegmentRegister::DS,
+                    Register::BX,
+                ));
+                def_use.def_registers(&[Register::AL]);
+            }
+            Mnemonic::CLC | Mnemonic::STC => def_use.def_flags(&[CpuFlag::CF]),
+            Mnemonic::CMC => {
+                def_use.use_flags(&[CpuFlag::CF]);
+                def_use.def_flags(&[CpuFlag::CF]);
+            }
+            Mnemonic::CLD | Mnemonic::STD => def_use.def_flags(&[CpuFlag::DF]),
+            Mnemonic::CLI | Mnemonic::STI => def_use.def_flags(&[CpuFlag::IF]),
+            Mnemonic::MOVS { .. } => {
+                def_use.string_source();
+                def_use.string_dest();
+                def_use.def_location(STRING_DEST);
+            }
+            Mnemonic::CMPS { .. } => {
+                def_use.string_source();
+                def_use.string_dest();
+                def_use.use_location(STRING_DEST);
+                def_use.def_flags(&ARITHMETIC_FLAGS);
+            }
+            Mnemonic::STOS { wide } => {
+                def_use.use_registers(&[accumulator(wide)]);
+                def_use.string_dest();
+                def_use.def_location(STRING_DEST);
+            }
+            Mnemonic::LODS { wide } => {
+                def_use.string_source();
+                def_use.def_registers(&[accumulator(wide)]);
+            }
+            Mnemonic::SCAS { wide } => {
+                def_use.use_registers(&[accumulator(wide)]);
+                def_use.string_dest();
+                def_use.use_location(STRING_DEST);
+                def_use.def_flags(&ARITHMETIC_FLAGS);
+            }
+            Mnemonic::REP | Mnemonic::REPNE => {
+                def_use.use_registers(&[Register::CX]);
+                def_use.use_flags(&[CpuFlag::ZF]);
+                def_use.def_registers(&[Register::CX]);
+            }
+            Mnemonic::LOOP { .. } => {
+                def_use.use_registers(&[Register::CX]);
+                def_use.def_registers(&[Register::CX]);
+            }
+            Mnemonic::LOOPE { .. } | Mnemonic::LOOPNE { .. } => {
+                def_use.use_registers(&[Register::CX]);
+                def_use.use_flags(&[CpuFlag::ZF]);
+                def_use.def_registers(&[Register::CX]);
+            }
+            Mnemonic::JCXZ { .. } => def_use.use_registers(&[Register::CX]),
+            Mnemonic::JO { .. } | Mnemonic::JNO { .. } => def_use.use_flags(&[CpuFlag::OF]),
+            Mnemonic::JB { .. } | Mnemonic::JNB { .. } => def_use.use_flags(&[CpuFlag::CF]),
+            Mnemonic::JE { .. } | Mnemonic::JNE { .. } => def_use.use_flags(&[CpuFlag::ZF]),
+            Mnemonic::JBE { .. } | Mnemonic::JNBE { .. } => {
+                def_use.use_flags(&[CpuFlag::CF, CpuFlag::ZF])
+            }
+            Mnemonic::JS { .. } | Mnemonic::JNS { .. } => def_use.use_flags(&[CpuFlag::SF]),
+            Mnemonic::JP { .. } | Mnemonic::JNP { .. } => def_use.use_flags(&[CpuFlag::PF]),
+            Mnemonic::JL { .. } | Mnemonic::JNL { .. } => {
+                def_use.use_flags(&[CpuFlag::SF, CpuFlag::OF])
+            }
+            Mnemonic::JLE { .. } | Mnemonic::JNLE { .. } => {
+                def_use.use_flags(&[CpuFlag::SF, CpuFlag::OF, CpuFlag::ZF])
+            }
+            Mnemonic::JMP { .. } => (),
+            Mnemonic::JMPFAR { .. } => {
+                def_use.def_location(Location::SegmentRegister(SegmentRegister::CS))
+            }
+            Mnemonic::CALL { far_proc, .. } => {
+                def_use.push();
+                if far_proc.is_some() {
+                    def_use.use_location(Location::SegmentRegister(SegmentRegister::CS));
+                    def_use.def_location(Location::SegmentRegister(SegmentRegister::CS));
+                }
+            }
+            Mnemonic::CALLINDIRECT { target, far } => {
+                def_use.read(target);
+                def_use.push();
+                if far {
+                    def_use.use_location(Location::SegmentRegister(SegmentRegister::CS));
+                    def_use.def_location(Location::SegmentRegister(SegmentRegister::CS));
+                }
+            }
+            Mnemonic::JMPINDIRECT { target, far } => {
+                def_use.read(target);
+                if far {
+                    def_use.def_location(Location::SegmentRegister(SegmentRegister::CS));
+                }
+            }
+            Mnemonic::RET { .. } => def_use.pop(),
+            Mnemonic::RETF { .. } => {
+                def_use.pop();
+                def_use.def_location(Location::SegmentRegister(SegmentRegister::CS));
+            }
+            Mnemonic::PUSH(source) => {
+                def_use.read(source);
+                def_use.push();
+            }
+            Mnemonic::POP(dest) => {
+                def_use.pop();
+                def_use.write(dest);
+            }
+            Mnemonic::PUSHSEG(segment) => {
+                def_use.use_location(Location::SegmentRegister(segment));
+                def_use.push();
+            }
+            Mnemonic::POPSEG(segment) => {
+                def_use.pop();
+                def_use.def_location(Location::SegmentRegister(segment));
+            }
+            Mnemonic::PUSHF => {
+                def_use.use_flags(&ALL_FLAGS);
+                def_use.push();
+            }
+            Mnemonic::POPF => {
+                def_use.pop();
+                def_use.def_flags(&ALL_FLAGS);
+            }
+            Mnemonic::INT { .. } | Mnemonic::INTO => {
+                if *self == Mnemonic::INTO {
+                    def_use.use_flags(&[CpuFlag::OF]);
+                }
+                def_use.use_flags(&ALL_FLAGS);
+                def_use.use_location(Location::SegmentRegister(SegmentRegister::CS));
+                def_use.push();
+                def_use.def_location(Location::SegmentRegister(SegmentRegister::CS));
+                def_use.def_flags(&[CpuFlag::IF, CpuFlag::TF]);
+            }
+            Mnemonic::IRET => {
+                def_use.pop();
+                def_use.def_location(Location::SegmentRegister(SegmentRegister::CS));
+                def_use.def_flags(&ALL_FLAGS);
+            }
+            // IN and OUT both keep the accumulator in `dest` and the port in `source`
+            Mnemonic::IN { dest, source } => {
+                def_use.read(source);
+                def_use.write(dest);
+            }
+            Mnemonic::OUT { dest, source } => {
+                def_use.read(source);
+                def_use.read(dest);
+            }
+            Mnemonic::SEGMENTOVERRIDE(segment) => {
+                def_use.use_location(Location::SegmentRegister(segment))
+            }
+            Mnemonic::ESC | Mnemonic::WAIT | Mnemonic::LOCK | Mnemonic::HLT | Mnemonic::NOP => (),
+        }
+        def_use
+    }
+
+    pub fn uses(&self) -> Vec<Location> {
+        self.def_use().uses
+    }
+
+    pub fn defs(&self) -> Vec<Location> {
+        self.def_use().defs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Location;
+    use crate::cpu::CpuFlag;
+    use crate::instructions::Mnemonic;
+    use crate::registers::{Register, RegisterMemory, SegmentRegister};
+
+    #[test]
+    fn test_implicit_operands() {
+        let mul = Mnemonic::MUL {
+            dest: RegisterMemory::Register(Register::AX),
+            source: RegisterMemory::Register(Register::BX),
+        };
+        assert!(mul.uses().contains(&Location::Register(Register::AX)));
+        assert!(mul.defs().contains(&Location::Register(Register::DX)));
+        let loop_ = Mnemonic::LOOP { short_label: 0xfe };
+        assert_eq!(loop_.uses(), vec![Location::Register(Register::CX)]);
+        assert_eq!(loop_.defs(), vec![Location::Register(Register::CX)]);
+        let push = Mnemonic::PUSH(RegisterMemory::Register(Register::BX));
+        assert!(push.defs().contains(&Location::Register(Register::SP)));
+        assert!(push
+            .defs()
+            .contains(&Location::ImplicitMemory(SegmentRegister::SS, Register::SP)));
+    }
+
+    #[test]
+    fn test_memory_operands() {
+        let add = Mnemonic::ADD {
+            dest: RegisterMemory::CombineRegistersData(Register::BP, Register::SI, 4),
+            source: RegisterMemory::Register(Register::CX),
+        };
+        let uses = add.uses();
+        assert!(uses.contains(&Location::Register(Register::BP)));
+        assert!(uses.contains(&Location::SegmentRegister(SegmentRegister::SS)));
+        assert!(uses.contains(&Location::Register(Register::CX)));
+        let defs = add.defs();
+        assert!(defs.contains(&Location::Memory(RegisterMemory::CombineRegistersData(
+            Register::BP,
+            Register::SI,
+            4
+        ))));
+        assert!(defs.contains(&Location::Flag(CpuFlag::CF)));
+        assert!(!defs.contains(&Location::Register(Register::CX)));
+    }
+}
@@ -0,0 +1,581 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+use crate::cpu::{Cpu, CpuFlag};
+use crate::exe::MzHeader;
+use crate::registers::{Register, SegmentRegister};
+use crate::Result;
+
+/// Where `load_com` puts the program segment prefix unless told otherwise, clear of the
+/// interrupt vector table and BIOS data area
+pub const PSP_SEGMENT: u16 = 0x1000;
+
+/// A .COM program starts right after its 256 byte program segment prefix
+pub const COM_OFFSET: u16 = 0x0100;
+
+/// The first segment past the memory a .COM program owns, which is all of conventional memory
+const MEMORY_TOP_SEGMENT: u16 = 0xa000;
+
+/// Handles 0, 1 and 2 are the standard input, output and error devices
+const FIRST_FILE_HANDLE: u16 = 5;
+const MAX_FILE_HANDLES: usize = 20;
+
+/// DOS error codes returned in AX with CF set
+const FILE_NOT_FOUND: u16 = 0x02;
+const PATH_NOT_FOUND: u16 = 0x03;
+const TOO_MANY_OPEN_FILES: u16 = 0x04;
+const ACCESS_DENIED: u16 = 0x05;
+const INVALID_HANDLE: u16 = 0x06;
+const INVALID_ACCESS_CODE: u16 = 0x0c;
+
+/// Copies a .COM program to `segment:0100` behind a minimal program segment prefix and points
+/// every segment register at it. The stack starts at the top of the segment with a zero word
+/// on it, so a near RET lands on the INT 20h at the start of the PSP
+pub fn load_com(cpu: &mut Cpu, segment: u16, program: &[u8], arguments: &str) -> Result<()> {
+    if program.len() > (0xfffe - COM_OFFSET) as usize {
+        return Err(format!("A .COM program can't be {} bytes long", program.len()).into());
+    }
+    write_psp(cpu, segment, arguments)?;
+    cpu.memory.load(segment, COM_OFFSET, program);
+    for register in [
+        SegmentRegister::CS,
+        SegmentRegister::DS,
+        SegmentRegister::ES,
+        SegmentRegister::SS,
+    ] {
+        cpu.set_segment_register(register, segment);
+    }
+    cpu.registers.ip = COM_OFFSET;
+    cpu.registers.set(Register::SP, 0xfffe);
+    cpu.memory.write_word(segment, 0xfffe, 0);
+    Ok(())
+}
+
+/// Loads an MZ executable in the paragraph after a program segment prefix at `segment`. CS:IP
+/// and SS:SP come from the header while DS and ES point at the PSP, like DOS leaves them
+pub fn load_exe(cpu: &mut Cpu, segment: u16, binary: &[u8], arguments: &str) -> Result<MzHeader> {
+    let header = MzHeader::parse(binary)?;
+    write_psp(cpu, segment, arguments)?;
+    header.load(cpu, segment.wrapping_add(COM_OFFSET / 16), binary);
+    cpu.set_segment_register(SegmentRegister::DS, segment);
+    cpu.set_segment_register(SegmentRegister::ES, segment);
+    Ok(header)
+}
+
+/// The PSP starts with an INT 20h, has the end of the program's memory at offset 2 and the
+/// command tail, its length byte followed by the text and a CR, at 0x80
+fn write_psp(cpu: &mut Cpu, segment: u16, arguments: &str) -> Result<()> {
+    let tail = arguments.as_bytes();
+    if tail.len() > 126 {
+        return Err("The command tail is longer than 126 bytes".into());
+    }
+    cpu.memory.load(segment, 0, &[0; COM_OFFSET as usize]);
+    cpu.memory.load(segment, 0x00, &[0xcd, 0x20]);
+    cpu.memory.write_word(segment, 0x02, MEMORY_TOP_SEGMENT);
+    cpu.memory.write_byte(segment, 0x80, tail.len() as u8);
+    cpu.memory.load(segment, 0x81, tail);
+    cpu.memory
+        .write_byte(segment, 0x81 + tail.len() as u16, b'\r');
+    Ok(())
+}
+
+/// Just enough of DOS to run small utilities: console I/O on INT 21h, files inside a host
+/// directory and termination through INT 21h or INT 20h
+pub struct Dos {
+    root: PathBuf,
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    files: HashMap<u16, File>,
+    exit_code: Option<u8>,
+}
+
+impl std::fmt::Debug for Dos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dos")
+            .field("root", &self.root)
+            .field("files", &self.files)
+            .field("exit_code", &self.exit_code)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Dos {
+    /// Programs can only open files below `root`, the keyboard reads from `input` and the
+    /// screen and standard error both write to `output`
+    pub fn new(root: impl Into<PathBuf>, input: Box<dyn BufRead>, output: Box<dyn Write>) -> Self {
+        Self {
+            root: root.into(),
+            input,
+            output,
+            files: HashMap::new(),
+            exit_code: None,
+        }
+    }
+
+    /// Hooks INT 20h and INT 21h on `cpu`, the returned handle gives access to the exit code
+    /// once the program has terminated
+    pub fn install(self, cpu: &mut Cpu) -> Rc<RefCell<Dos>> {
+        let dos = Rc::new(RefCell::new(self));
+        let services = Rc::clone(&dos);
+        cpu.hook_interrupt(0x21, None, move |cpu| services.borrow_mut().service(cpu));
+        let terminate = Rc::clone(&dos);
+        cpu.hook_interrupt(0x20, None, move |_| {
+            terminate.borrow_mut().terminate(0);
+            Ok(())
+        });
+        dos
+    }
+
+    /// The AL a program passed to function 4Ch, or 0 for the other ways of terminating
+    pub fn exit_code(&self) -> Option<u8> {
+        self.exit_code
+    }
+
+    fn service(&mut self, cpu: &mut Cpu) -> Result<()> {
+        let function = (cpu.registers.get(Register::AX) >> 8) as u8;
+        match function {
+            0x00 => self.terminate(0),
+            0x01 => {
+                let character = self.read_character()?;
+                self.output.write_all(&[character])?;
+                self.output.flush()?;
+                cpu.registers.set(Register::AL, character as u16);
+            }
+            0x02 => {
+                let character = cpu.registers.get(Register::DL);
+                self.output.write_all(&[character as u8])?;
+                self.output.flush()?;
+                cpu.registers.set(Register::AL, character);
+            }
+            0x09 => {
+                let (segment, offset) = buffer_address(cpu);
+                let mut string = Vec::new();
+                for i in 0..=u16::MAX {
+                    match cpu.memory.read_byte(segment, offset.wrapping_add(i)) {
+                        b'$' => break,
+                        byte => string.push(byte),
+                    }
+                }
+                self.output.write_all(&string)?;
+                self.output.flush()?;
+                cpu.registers.set(Register::AL, b'$' as u16);
+            }
+            0x0a => self.buffered_input(cpu)?,
+            0x3c => {
+                let result = self.host_path(cpu).and_then(|path| {
+                    let file = OpenOptions::new()
+                        .read(true)
+                        .write(true)
+                        .create(true)
+                        .truncate(true)
+                        .open(path)
+                        .map_err(|error| error_code(&error))?;
+                    self.allocate_handle(file)
+                });
+                finish(cpu, result);
+            }
+            0x3d => {
+                let result = self.host_path(cpu).and_then(|path| {
+                    let mut options = OpenOptions::new();
+                    match cpu.registers.get(Register::AL) & 0x07 {
+                        0 => options.read(true),
+                        1 => options.write(true),
+                        2 => options.read(true).write(true),
+                        _ => return Err(INVALID_ACCESS_CODE),
+                    };
+                    let file = options.open(path).map_err(|error| error_code(&error))?;
+                    self.allocate_handle(file)
+                });
+                finish(cpu, result);
+            }
+            0x3e => {
+                let handle = cpu.registers.get(Register::BX);
+                let result = match self.files.remove(&handle) {
+                    Some(_) => Ok(0),
+                    None => Err(INVALID_HANDLE),
+                };
+                finish(cpu, result);
+            }
+            0x3f => {
+                let result = self.read_handle(cpu);
+                finish(cpu, result);
+            }
+            0x40 => {
+                let result = self.write_handle(cpu);
+                finish(cpu, result);
+            }
+            0x4c => {
+                let code = cpu.registers.get(Register::AL) as u8;
+                self.terminate(code);
+            }
+            _ => return Err(format!("Unsupported INT 21h function {:02x}h", function).into()),
+        }
+        Ok(())
+    }
+
+    fn terminate(&mut self, code: u8) {
+        self.files.clear();
+        self.exit_code = Some(code);
+    }
+
+    /// Reads one byte from the keyboard, the end of the input reads as Ctrl-Z
+    fn read_character(&mut self) -> Result<u8> {
+        let character = self.input.fill_buf()?.first().copied();
+        match character {
+            Some(character) => {
+                self.input.consume(1);
+                Ok(character)
+            }
+            None => Ok(0x1a),
+        }
+    }
+
+    /// Function 0Ah reads a line into DS:DX, the first byte of the buffer is its size and DOS
+    /// fills in the number of characters read and the characters themselves ending with CR
+    fn buffered_input(&mut self, cpu: &mut Cpu) -> Result<()> {
+        let (segment, offset) = buffer_address(cpu);
+        let size = cpu.memory.read_byte(segment, offset) as usize;
+        if size == 0 {
+            return Ok(());
+        }
+        let mut line = Vec::new();
+        self.input.read_until(b'\n', &mut line)?;
+        while matches!(line.last(), Some(b'\n' | b'\r')) {
+            line.pop();
+        }
+        line.truncate(size - 1);
+        self.output.write_all(&line)?;
+        self.output.write_all(b"\r\n")?;
+        self.output.flush()?;
+        cpu.memory
+            .write_byte(segment, offset.wrapping_add(1), line.len() as u8);
+        line.push(b'\r');
+        for (i, byte) in line.into_iter().enumerate() {
+            cpu.memory
+                .write_byte(segment, offset.wrapping_add(2 + i as u16), byte);
+        }
+        Ok(())
+    }
+
+    /// Function 3Fh reads up to CX bytes from handle BX into DS:DX
+    fn read_handle(&mut self, cpu: &mut Cpu) -> std::result::Result<u16, u16> {
+        let handle = cpu.registers.get(Register::BX);
+        let mut buffer = vec![0; cpu.registers.get(Register::CX) as usize];
+        let count = match handle {
+            0 => self.input.read(&mut buffer),
+            _ => match self.files.get_mut(&handle) {
+                Some(file) => file.read(&mut buffer),
+                None => return Err(INVALID_HANDLE),
+            },
+        }
+        .map_err(|error| error_code(&error))?;
+        let (segment, offset) = buffer_address(cpu);
+        for (i, byte) in buffer[..count].iter().enumerate() {
+            cpu.memory
+                .write_byte(segment, offset.wrapping_add(i as u16), *byte);
+        }
+        Ok(count as u16)
+    }
+
+    /// Function 40h writes CX bytes from DS:DX to handle BX
+    fn write_handle(&mut self, cpu: &mut Cpu) -> std::result::Result<u16, u16> {
+        let handle = cpu.registers.get(Register::BX);
+        let (segment, offset) = buffer_address(cpu);
+        let buffer: Vec<u8> = (0..cpu.registers.get(Register::CX))
+            .map(|i| cpu.memory.read_byte(segment, offset.wrapping_add(i)))
+            .collect();
+        let written = match handle {
+            1 | 2 => self
+                .output
+                .write_all(&buffer)
+                .and_then(|_| self.output.flush()),
+            _ => match self.files.get_mut(&handle) {
+                Some(file) => file.write_all(&buffer),
+                None => return Err(INVALID_HANDLE),
+            },
+        };
+        written.map_err(|error| error_code(&error))?;
+        Ok(buffer.len() as u16)
+    }
+
+    fn allocate_handle(&mut self, file: File) -> std::result::Result<u16, u16> {
+        if self.files.len() >= MAX_FILE_HANDLES {
+            return Err(TOO_MANY_OPEN_FILES);
+        }
+        let handle = (FIRST_FILE_HANDLE..)
+            .find(|handle| !self.files.contains_key(handle))
+            .ok_or(TOO_MANY_OPEN_FILES)?;
+        self.files.insert(handle, file);
+        Ok(handle)
+    }
+
+    /// Resolves the ASCIIZ file name at DS:DX inside the sandbox, drive letters, absolute
+    /// paths and `..` are refused and symlinks have to stay inside `root` too, so a program
+    /// can't reach anything outside of it
+    fn host_path(&self, cpu: &Cpu) -> std::result::Result<PathBuf, u16> {
+        let (segment, offset) = buffer_address(cpu);
+        let name: Vec<u8> = (0..u16::MAX)
+            .map(|i| cpu.memory.read_byte(segment, offset.wrapping_add(i)))
+            .take_while(|byte| *byte != 0)
+            .collect();
+        let name = String::from_utf8_lossy(&name).replace('\\', "/");
+        if name.is_empty() || name.contains(':') {
+            return Err(PATH_NOT_FOUND);
+        }
+        let relative = Path::new(&name);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(ACCESS_DENIED);
+        }
+        let path = self.root.join(relative);
+        let root = self.root.canonicalize().map_err(|_| PATH_NOT_FOUND)?;
+        let resolved = match path.canonicalize() {
+            Ok(resolved) => resolved,
+            // a dangling symlink would be followed when the file is created
+            Err(_) if path.symlink_metadata().is_ok() => return Err(ACCESS_DENIED),
+            // a file that doesn't exist yet only needs its directory to be inside the root
+            Err(_) => {
+                let directory = path.parent().ok_or(PATH_NOT_FOUND)?;
+                let name = path.file_name().ok_or(PATH_NOT_FOUND)?;
+                let directory = directory.canonicalize().map_err(|_| PATH_NOT_FOUND)?;
+                directory.join(name)
+            }
+        };
+        if !resolved.starts_with(&root) {
+            return Err(ACCESS_DENIED);
+        }
+        Ok(resolved)
+    }
+}
+
+/// The DS:DX pair most INT 21h functions take their buffer or file name in
+fn buffer_address(cpu: &Cpu) -> (u16, u16) {
+    (
+        cpu.segment_register(SegmentRegister::DS),
+        cpu.registers.get(Register::DX),
+    )
+}
+
+/// DOS reports success with CF clear and the result in AX, failures set CF and put the error
+/// code in AX instead
+fn finish(cpu: &mut Cpu, result: std::result::Result<u16, u16>) {
+    let (carry, value) = match result {
+        Ok(value) => (false, value),
+        Err(code) => (true, code),
+    };
+    cpu.registers.set_flag(CpuFlag::CF, carry);
+    cpu.registers.set(Register::AX, value);
+}
+
+fn error_code(error: &std::io::Error) -> u16 {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => FILE_NOT_FOUND,
+        _ => ACCESS_DENIED,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{Cursor, Write};
+    use std::rc::Rc;
+
+    use super::{load_com, load_exe, Dos, PSP_SEGMENT};
+    use crate::cpu::{Cpu, CpuFlag};
+    use crate::exe::tests::executable;
+    use crate::instructions::Mnemonic;
+    use crate::registers::{Register, SegmentRegister};
+
+    /// Collects what the program printed so the test can look at it afterwards
+    #[derive(Clone, Default)]
+    struct Screen(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Screen {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn machine(input: &str, root: &std::path::Path) -> (Cpu, Rc<RefCell<Dos>>, Screen) {
+        let mut cpu = Cpu::new();
+        let screen = Screen::default();
+        let dos = Dos::new(
+            root,
+            Box::new(Cursor::new(input.as_bytes().to_vec())),
+            Box::new(screen.clone()),
+        )
+        .install(&mut cpu);
+        (cpu, dos, screen)
+    }
+
+    fn int21(cpu: &mut Cpu, ax: u16) {
+        cpu.registers.set(Register::AX, ax);
+        cpu.execute(Mnemonic::INT { value: 0x21 }).unwrap();
+    }
+
+    #[test]
+    fn test_com_program() {
+        let (mut cpu, dos, screen) = machine("", &std::env::temp_dir());
+        // mov ah, 9; mov dx, msg; int 21h; mov dl, '!'; mov ah, 2; int 21h; mov ax, 0x4c03;
+        // int 21h; msg: db "Hi$"
+        let program = [
+            &[
+                0xb4, 0x09, 0xba, 0x12, 0x01, 0xcd, 0x21, 0xb2, 0x21, 0xb4, 0x02, 0xcd, 0x21, 0xb8,
+                0x03, 0x4c, 0xcd, 0x21,
+            ][..],
+            b"Hi$",
+        ]
+        .concat();
+        load_com(&mut cpu, PSP_SEGMENT, &program, " /v").unwrap();
+        assert_eq!(cpu.memory.read_byte(PSP_SEGMENT, 0x80), 3);
+        assert_eq!(cpu.memory.read_byte(PSP_SEGMENT, 0x84), b'\r');
+        assert_eq!(cpu.segment_register(SegmentRegister::SS), PSP_SEGMENT);
+        while dos.borrow().exit_code().is_none() {
+            cpu.step().unwrap();
+        }
+        assert_eq!(screen.0.borrow().as_slice(), b"Hi!");
+        assert_eq!(dos.borrow().exit_code(), Some(3));
+
+        // a bare RET returns to the INT 20h at the start of the PSP
+        let (mut cpu, dos, _) = machine("", &std::env::temp_dir());
+        load_com(&mut cpu, PSP_SEGMENT, &[0xc3], "").unwrap();
+        while dos.borrow().exit_code().is_none() {
+            cpu.step().unwrap();
+        }
+        assert_eq!(dos.borrow().exit_code(), Some(0));
+
+        // HLT isn't a way to exit, and nothing can wake the cpu back up
+        let (mut cpu, dos, _) = machine("", &std::env::temp_dir());
+        load_com(&mut cpu, PSP_SEGMENT, &[0xfb, 0xf4], "").unwrap();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert!(cpu.halted() && !cpu.can_wake());
+        assert_eq!(dos.borrow().exit_code(), None);
+    }
+
+    #[test]
+    fn test_exe_program() {
+        let (mut cpu, dos, screen) = machine("", &std::env::temp_dir());
+        // mov ax, seg text; mov ds, ax; mov dx, 0; mov ah, 9; int 21h; mov ax, 0x4c01; int 21h
+        // with text: db "MZ$" two paragraphs in
+        let mut image = vec![
+            0xb8, 0x02, 0x00, 0x8e, 0xd8, 0xba, 0x00, 0x00, 0xb4, 0x09, 0xcd, 0x21, 0xb8, 0x01,
+            0x4c, 0xcd, 0x21,
+        ];
+        image.resize(0x30, 0);
+        image[0x20..0x23].copy_from_slice(b"MZ$");
+        let binary = executable(&image, &[(0x0001, 0x0000)], (0, 0));
+        let header = load_exe(&mut cpu, PSP_SEGMENT, &binary, "").unwrap();
+        assert_eq!(header.relocations, vec![(0x0001, 0x0000)]);
+        assert_eq!(
+            cpu.segment_register(SegmentRegister::CS),
+            PSP_SEGMENT + 0x10
+        );
+        assert_eq!(cpu.segment_register(SegmentRegister::ES), PSP_SEGMENT);
+        while dos.borrow().exit_code().is_none() {
+            cpu.step().unwrap();
+        }
+        assert_eq!(screen.0.borrow().as_slice(), b"MZ");
+        assert_eq!(dos.borrow().exit_code(), Some(1));
+    }
+
+    #[test]
+    fn test_console_input() {
+        let (mut cpu, _, screen) = machine("yhello world\n", &std::env::temp_dir());
+        int21(&mut cpu, 0x0100);
+        assert_eq!(cpu.registers.get(Register::AL), b'y' as u16);
+        // a six byte buffer holds five characters and the CR
+        cpu.memory.write_byte(0, 0x200, 6);
+        cpu.registers.set(Register::DX, 0x200);
+        int21(&mut cpu, 0x0a00);
+        assert_eq!(cpu.memory.read_byte(0, 0x201), 5);
+        let line: Vec<u8> = (0x202..0x208).map(|i| cpu.memory.read_byte(0, i)).collect();
+        assert_eq!(line, b"hello\r");
+        assert_eq!(screen.0.borrow().as_slice(), b"yhello\r\n");
+        // the end of the input reads as Ctrl-Z
+        int21(&mut cpu, 0x0100);
+        assert_eq!(cpu.registers.get(Register::AL), 0x1a);
+    }
+
+    #[test]
+    fn test_sandboxed_files() {
+        let root =
+            std::env::temp_dir().join(format!("computer_enhance_dos_{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let (mut cpu, _, screen) = machine("", &root);
+        cpu.memory.load(0, 0x100, b"NOTES.TXT\0");
+        cpu.memory.load(0, 0x200, b"contents");
+        cpu.registers.set(Register::DX, 0x100);
+        int21(&mut cpu, 0x3c00);
+        assert!(!cpu.flag(CpuFlag::CF));
+        let handle = cpu.registers.get(Register::AX);
+        assert_eq!(handle, 5);
+        cpu.registers.set(Register::BX, handle);
+        cpu.registers.set(Register::CX, 8);
+        cpu.registers.set(Register::DX, 0x200);
+        int21(&mut cpu, 0x4000);
+        assert_eq!(cpu.registers.get(Register::AX), 8);
+        int21(&mut cpu, 0x3e00);
+        assert!(!cpu.flag(CpuFlag::CF));
+        assert_eq!(std::fs::read(root.join("NOTES.TXT")).unwrap(), b"contents");
+
+        cpu.registers.set(Register::DX, 0x100);
+        int21(&mut cpu, 0x3d00);
+        cpu.registers
+            .set(Register::BX, cpu.registers.get(Register::AX));
+        cpu.registers.set(Register::CX, 0x40);
+        cpu.registers.set(Register::DX, 0x300);
+        int21(&mut cpu, 0x3f00);
+        assert_eq!(cpu.registers.get(Register::AX), 8);
+        assert_eq!(cpu.memory.read_byte(0, 0x307), b's');
+        // the file goes to the screen through the standard output handle
+        cpu.registers.set(Register::BX, 1);
+        cpu.registers.set(Register::CX, 8);
+        int21(&mut cpu, 0x4000);
+        assert_eq!(screen.0.borrow().as_slice(), b"contents");
+
+        // closing twice and escaping the sandbox both fail with CF set
+        cpu.registers.set(Register::BX, 5);
+        int21(&mut cpu, 0x3e00);
+        int21(&mut cpu, 0x3e00);
+        assert!(cpu.flag(CpuFlag::CF));
+        assert_eq!(cpu.registers.get(Register::AX), 0x06);
+        for name in [&b"..\\SECRET\0"[..], b"C:\\AUTOEXEC.BAT\0", b"MISSING\0"] {
+            cpu.memory.load(0, 0x100, name);
+            cpu.registers.set(Register::DX, 0x100);
+            int21(&mut cpu, 0x3d00);
+            assert!(cpu.flag(CpuFlag::CF));
+        }
+        assert_eq!(cpu.registers.get(Register::AX), 0x02);
+
+        // symlinks out of the sandbox are refused, even ones that don't point anywhere yet
+        #[cfg(unix)]
+        {
+            let outside = root.with_extension("outside");
+            std::fs::write(&outside, b"secret").unwrap();
+            std::os::unix::fs::symlink(&outside, root.join("LINK")).unwrap();
+            let created = root.with_extension("new");
+            std::os::unix::fs::symlink(&created, root.join("DANGLING")).unwrap();
+            for (name, function) in [(&b"LINK\0"[..], 0x3d00), (b"DANGLING\0", 0x3c00)] {
+                cpu.memory.load(0, 0x100, name);
+                cpu.registers.set(Register::DX, 0x100);
+                int21(&mut cpu, function);
+                assert!(cpu.flag(CpuFlag::CF));
+                assert_eq!(cpu.registers.get(Register::AX), 0x05);
+            }
+            assert!(!created.exists());
+            std::fs::remove_file(&outside).unwrap();
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
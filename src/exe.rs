@@ -0,0 +1,200 @@
+use crate::cpu::Cpu;
+use crate::registers::{Register, SegmentRegister};
+use crate::Result;
+
+/// The fixed part of an MZ header is 14 words, starting with the "MZ" signature
+const HEADER_LENGTH: usize = 28;
+const PAGE_SIZE: usize = 512;
+const PARAGRAPH_SIZE: usize = 16;
+
+/// The header of a DOS MZ executable, with the relocation table that follows it. Segments
+/// are relative to the segment the load image is copied to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MzHeader {
+    /// Bytes used in the last 512 byte page, 0 when it is full
+    pub last_page_bytes: u16,
+    /// 512 byte pages in the file, including the header and the last partial page
+    pub pages: u16,
+    pub header_paragraphs: u16,
+    pub min_extra_paragraphs: u16,
+    pub max_extra_paragraphs: u16,
+    pub ss: u16,
+    pub sp: u16,
+    pub checksum: u16,
+    pub ip: u16,
+    pub cs: u16,
+    pub relocation_table_offset: u16,
+    pub overlay: u16,
+    /// `offset, segment` pairs pointing at the words that need the load segment added to them
+    pub relocations: Vec<(u16, u16)>,
+}
+
+impl MzHeader {
+    pub fn parse(binary: &[u8]) -> Result<Self> {
+        if binary.len() < HEADER_LENGTH || !binary.starts_with(b"MZ") {
+            return Err("Not an MZ executable".into());
+        }
+        let word = |offset: usize| u16::from_le_bytes([binary[offset], binary[offset + 1]]);
+        let mut header = Self {
+            last_page_bytes: word(0x02),
+            pages: word(0x04),
+            header_paragraphs: word(0x08),
+            min_extra_paragraphs: word(0x0a),
+            max_extra_paragraphs: word(0x0c),
+            ss: word(0x0e),
+            sp: word(0x10),
+            checksum: word(0x12),
+            ip: word(0x14),
+            cs: word(0x16),
+            relocation_table_offset: word(0x18),
+            overlay: word(0x1a),
+            relocations: Vec::new(),
+        };
+        if header.last_page_bytes as usize > PAGE_SIZE {
+            return Err("The last page is longer than a page".into());
+        }
+        if header.image_start() > header.file_length() {
+            return Err("The header is longer than the executable".into());
+        }
+        let table = header.relocation_table_offset as usize;
+        let count = word(0x06) as usize;
+        if table + count * 4 > binary.len() {
+            return Err("The relocation table is past the end of the file".into());
+        }
+        header.relocations = (0..count)
+            .map(|i| (word(table + i * 4), word(table + i * 4 + 2)))
+            .collect();
+        if header.image_start() + header.image_length() > binary.len() {
+            return Err("The load image is past the end of the file".into());
+        }
+        Ok(header)
+    }
+
+    /// Where the load image starts in the file, right after the header
+    pub fn image_start(&self) -> usize {
+        self.header_paragraphs as usize * PARAGRAPH_SIZE
+    }
+
+    /// The size of the load image, which is everything the page count covers minus the header
+    pub fn image_length(&self) -> usize {
+        self.file_length().saturating_sub(self.image_start())
+    }
+
+    /// The length of the executable the header describes, `parse` has made sure the last page
+    /// isn't longer than a page
+    fn file_length(&self) -> usize {
+        let length = self.pages as usize * PAGE_SIZE;
+        match self.last_page_bytes {
+            0 => length,
+            bytes => length.saturating_sub(PAGE_SIZE - bytes as usize),
+        }
+    }
+
+    /// Copies the load image to `segment:0000`, fixes up the relocations and sets CS:IP and
+    /// SS:SP to the entry point and stack from the header
+    pub fn load(&self, cpu: &mut Cpu, segment: u16, binary: &[u8]) {
+        let start = self.image_start();
+        cpu.memory
+            .load(segment, 0, &binary[start..start + self.image_length()]);
+        for &(offset, relative) in &self.relocations {
+            let target = segment.wrapping_add(relative);
+            let value = cpu.memory.read_word(target, offset);
+            cpu.memory
+                .write_word(target, offset, value.wrapping_add(segment));
+        }
+        cpu.set_segment_register(SegmentRegister::CS, segment.wrapping_add(self.cs));
+        cpu.set_segment_register(SegmentRegister::SS, segment.wrapping_add(self.ss));
+        cpu.registers.ip = self.ip;
+        cpu.registers.set(Register::SP, self.sp);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::MzHeader;
+    use crate::cpu::Cpu;
+    use crate::registers::{Register, SegmentRegister};
+
+    /// Wraps `image` in a header with the relocation table at 0x1c, padded to a paragraph
+    pub(crate) fn executable(
+        image: &[u8],
+        relocations: &[(u16, u16)],
+        cs_ip: (u16, u16),
+    ) -> Vec<u8> {
+        let header_length = (0x1c + relocations.len() * 4).next_multiple_of(16);
+        let length = header_length + image.len();
+        let words = [
+            u16::from_le_bytes(*b"MZ"),
+            (length % 512) as u16,
+            length.div_ceil(512) as u16,
+            relocations.len() as u16,
+            (header_length / 16) as u16,
+            0,
+            0xffff,
+            // a 256 byte stack in the paragraph after the image
+            (image.len() / 16 + 1) as u16,
+            0x0100,
+            0,
+            cs_ip.1,
+            cs_ip.0,
+            0x1c,
+            0,
+        ];
+        let mut binary: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        for (offset, segment) in relocations {
+            binary.extend_from_slice(&offset.to_le_bytes());
+            binary.extend_from_slice(&segment.to_le_bytes());
+        }
+        binary.resize(header_length, 0);
+        binary.extend_from_slice(image);
+        binary
+    }
+
+    #[test]
+    fn test_parse_header() {
+        assert!(MzHeader::parse(b"not an executable at all, no").is_err());
+        let binary = executable(&[0x90; 40], &[(0x0001, 0x0000)], (0x0001, 0x0004));
+        let header = MzHeader::parse(&binary).unwrap();
+        assert_eq!(header.image_start(), 32);
+        assert_eq!(header.image_length(), 40);
+        assert_eq!(header.relocations, vec![(0x0001, 0x0000)]);
+        assert_eq!((header.cs, header.ip), (0x0001, 0x0004));
+        assert!(MzHeader::parse(&binary[..60]).is_err());
+    }
+
+    #[test]
+    fn test_malformed_headers() {
+        let binary = executable(&[0x90; 40], &[], (0, 0));
+        let patched = |offset: usize, value: u16| {
+            let mut binary = binary.clone();
+            binary[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+            binary
+        };
+        // a last page longer than a page
+        assert!(MzHeader::parse(&patched(0x02, 0xffff)).is_err());
+        // an image that runs past the end of the file
+        assert!(MzHeader::parse(&patched(0x04, 2)).is_err());
+        // a header bigger than the whole executable
+        assert!(MzHeader::parse(&patched(0x08, 0x10)).is_err());
+    }
+
+    #[test]
+    fn test_load_relocations() {
+        let mut image = vec![0; 0x20];
+        // mov ax, seg data at 0000:0000 and a segment of 0001 stored at 0001:0004
+        image[..3].copy_from_slice(&[0xb8, 0x01, 0x00]);
+        image[0x14..0x16].copy_from_slice(&[0x01, 0x00]);
+        let binary = executable(&image, &[(0x0001, 0x0000), (0x0004, 0x0001)], (0, 0));
+        let mut cpu = Cpu::new();
+        let header = MzHeader::parse(&binary).unwrap();
+        header.load(&mut cpu, 0x2000, &binary);
+        assert_eq!(header.relocations.len(), 2);
+        assert_eq!(cpu.memory.read_word(0x2000, 0x0001), 0x2001);
+        assert_eq!(cpu.memory.read_word(0x2001, 0x0004), 0x2001);
+        assert_eq!(cpu.segment_register(SegmentRegister::CS), 0x2000);
+        assert_eq!(cpu.segment_register(SegmentRegister::SS), 0x2003);
+        assert_eq!(cpu.registers.get(Register::SP), 0x0100);
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.get(Register::AX), 0x2001);
+    }
+}
@@ -1,5 +1,4 @@
 use crate::{
-    parse_twos_complement_int,
     registers::{
         Mode, Register, RegisterEncoding, RegisterMemory, RegisterMemoryEncoding, SegmentRegister,
     },
@@ -33,7 +32,7 @@ impl TryFrom<u8> for ComparisonOperator {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum LogicOperator {
     SHL,
     SHR,
@@ -60,6 +59,34 @@ impl TryFrom<u8> for LogicOperator {
     }
 }
 
+/// The INC/DEC/CALL/JMP/PUSH group shared by the 0xFE and 0xFF opcodes
+#[derive(Debug, Eq, PartialEq)]
+pub enum GroupOperator {
+    INC,
+    DEC,
+    CALL,
+    CALLFAR,
+    JMP,
+    JMPFAR,
+    PUSH,
+}
+
+impl TryFrom<u8> for GroupOperator {
+    type Error = Box<dyn std::error::Error + 'static>;
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(Self::INC),
+            1 => Ok(Self::DEC),
+            2 => Ok(Self::CALL),
+            3 => Ok(Self::CALLFAR),
+            4 => Ok(Self::JMP),
+            5 => Ok(Self::JMPFAR),
+            6 => Ok(Self::PUSH),
+            _ => Err("Not a group operator".into()),
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum ImmediateMode {
     ADD,
@@ -94,6 +121,7 @@ pub struct LogicOperatorEncoding {
     operator: LogicOperator,
     dest: RegisterMemory,
     source: RegisterMemory,
+    wide: bool,
 }
 
 impl From<LogicOperatorEncoding> for Mnemonic {
@@ -102,33 +130,34 @@ impl From<LogicOperatorEncoding> for Mnemonic {
             operator,
             dest,
             source,
+            wide,
         } = value;
         match operator {
-            LogicOperator::SHL => Mnemonic::SAL { dest, source },
-            LogicOperator::SHR => Mnemonic::SHR { dest, source },
-            LogicOperator::SAR => Mnemonic::SAR { dest, source },
-            LogicOperator::ROL => Mnemonic::ROL { dest, source },
-            LogicOperator::ROR => Mnemonic::ROR { dest, source },
-            LogicOperator::RCL => Mnemonic::RCL { dest, source },
-            LogicOperator::RCR => Mnemonic::RCR { dest, source },
+            LogicOperator::SHL => Mnemonic::SAL { dest, source, wide },
+            LogicOperator::SHR => Mnemonic::SHR { dest, source, wide },
+            LogicOperator::SAR => Mnemonic::SAR { dest, source, wide },
+            LogicOperator::ROL => Mnemonic::ROL { dest, source, wide },
+            LogicOperator::ROR => Mnemonic::ROR { dest, source, wide },
+            LogicOperator::RCL => Mnemonic::RCL { dest, source, wide },
+            LogicOperator::RCR => Mnemonic::RCR { dest, source, wide },
         }
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Mnemonic {
     CBW,
     STI,
     XLAT,
     ESC,
     LOOPNE {
-        short_label: isize,
+        short_label: u8,
     },
     LOOPE {
-        short_label: isize,
+        short_label: u8,
     },
     LOOP {
-        short_label: isize,
+        short_label: u8,
     },
     IN {
         dest: RegisterMemory,
@@ -140,38 +169,50 @@ pub enum Mnemonic {
     },
     INTO,
     IRET,
-    AAM,
-    AAD,
+    /// AAM and AAD divide and multiply by `base`, which assemblers always encode as 10
+    AAM {
+        base: u8,
+    },
+    AAD {
+        base: u8,
+    },
     SAR {
         dest: RegisterMemory,
         source: RegisterMemory,
+        wide: bool,
     },
     SHR {
         dest: RegisterMemory,
         source: RegisterMemory,
+        wide: bool,
     },
     SAL {
         dest: RegisterMemory,
         source: RegisterMemory,
+        wide: bool,
     },
     RCR {
         dest: RegisterMemory,
         source: RegisterMemory,
+        wide: bool,
     },
     RCL {
         dest: RegisterMemory,
         source: RegisterMemory,
+        wide: bool,
     },
     ROR {
         dest: RegisterMemory,
         source: RegisterMemory,
+        wide: bool,
     },
     ROL {
         dest: RegisterMemory,
         source: RegisterMemory,
+        wide: bool,
     },
     INT {
-        value: isize,
+        value: u8,
     },
     LDS {
         dest: RegisterMemory,
@@ -182,7 +223,10 @@ pub enum Mnemonic {
         source: RegisterMemory,
     },
     RET {
-        segment: Option<isize>,
+        segment: Option<u16>,
+    },
+    RETF {
+        segment: Option<u16>,
     },
     MOVS {
         wide: bool,
@@ -206,8 +250,13 @@ pub enum Mnemonic {
     LAHF,
     CWD,
     CALL {
-        near_proc: Option<isize>,
-        far_proc: Option<isize>,
+        near_proc: Option<i16>,
+        far_proc: Option<(u16, u16)>,
+    },
+    /// CALL through a register or memory operand, a far target is a dword in memory
+    CALLINDIRECT {
+        target: RegisterMemory,
+        far: bool,
     },
     LEA {
         dest: RegisterMemory,
@@ -217,16 +266,22 @@ pub enum Mnemonic {
         dest: RegisterMemory,
         source: RegisterMemory,
     },
-    INC(Register),
-    DEC(Register),
+    INC {
+        dest: RegisterMemory,
+        wide: bool,
+    },
+    DEC {
+        dest: RegisterMemory,
+        wide: bool,
+    },
     SEGMENTOVERRIDE(SegmentRegister),
     AAS,
     AAA,
     DAA,
     DAS,
     NOP,
-    PUSH(Register),
-    POP(Register),
+    PUSH(RegisterMemory),
+    POP(RegisterMemory),
     PUSHSEG(SegmentRegister),
     POPSEG(SegmentRegister),
     XCHG {
@@ -266,7 +321,15 @@ pub enum Mnemonic {
         source: RegisterMemory,
     },
     JMP {
-        label: isize,
+        label: i16,
+    },
+    JMPFAR {
+        segment: u16,
+        offset: u16,
+    },
+    JMPINDIRECT {
+        target: RegisterMemory,
+        far: bool,
     },
     LOCK,
     REPNE,
@@ -330,11 +393,11 @@ pub enum Mnemonic {
     },
     NOT {
         dest: RegisterMemory,
-        source: RegisterMemory,
+        wide: bool,
     },
     NEG {
         dest: RegisterMemory,
-        source: RegisterMemory,
+        wide: bool,
     },
     MUL {
         dest: RegisterMemory,
@@ -359,24 +422,35 @@ pub enum Mnemonic {
     STD,
 }
 
+/// A decoded instruction and where it was found in the binary
+#[derive(Debug, Eq, PartialEq)]
+pub struct Instruction {
+    pub offset: usize,
+    pub length: usize,
+    pub mnemonic: Mnemonic,
+}
+
 pub struct ImmediateModeEncoding<I> {
     mode: ImmediateMode,
     dest: RegisterMemory,
     wide: bool,
+    sign_extend: bool,
     iter: I,
 }
 
 impl<'a, I: Iterator<Item = &'a u8>> TryFrom<ImmediateModeEncoding<I>> for Mnemonic {
     type Error = Box<dyn std::error::Error + 'static>;
     fn try_from(mut value: ImmediateModeEncoding<I>) -> Result<Self> {
-        let source: RegisterMemory;
-        if value.wide {
+        let source = if value.sign_extend {
+            let data = *value.iter.next().unwrap() as i8;
+            RegisterMemory::ImmediateWide(data as u16)
+        } else if value.wide {
             let data =
                 u16::from_le_bytes([*value.iter.next().unwrap(), *value.iter.next().unwrap()]);
-            source = RegisterMemory::Immediate(data as isize);
+            RegisterMemory::ImmediateWide(data)
         } else {
-            source = RegisterMemory::Immediate(*value.iter.next().unwrap() as isize);
-        }
+            RegisterMemory::Immediate(*value.iter.next().unwrap())
+        };
         let mnemonic = match value.mode {
             ImmediateMode::ADD => Mnemonic::ADD {
                 dest: value.dest,
@@ -427,7 +501,7 @@ pub fn register_memory_register<'a, I: Iterator<Item = &'a u8>>(
     reversed: bool,
 ) -> Result<(RegisterMemory, RegisterMemory)> {
     let data_byte = iter.next().unwrap();
-    let mode = get_mode(&data_byte)?;
+    let mode = get_mode(data_byte)?;
     let (source, dest): (RegisterMemory, RegisterMemory);
     let rm = data_byte & 7;
     let byte = (data_byte >> 3) & 7;
@@ -457,7 +531,7 @@ pub fn immediate_to_memory<'a, I: Iterator<Item = &'a u8>>(
 ) -> Result<Mnemonic> {
     let data_byte = iter.next().unwrap();
     let rm = data_byte & 7;
-    let mode = get_mode(&data_byte)?;
+    let mode = get_mode(data_byte)?;
     let rm_encoding = RegisterMemoryEncoding {
         mode,
         rm,
@@ -465,17 +539,13 @@ pub fn immediate_to_memory<'a, I: Iterator<Item = &'a u8>>(
         iter: &mut iter,
     };
     let dest = RegisterMemory::try_from(rm_encoding)?;
-    let mut operand: isize;
-    if wide {
-        operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]) as isize;
+    let source = if wide {
+        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+        RegisterMemory::ImmediateWide(data)
     } else {
-        operand = *iter.next().unwrap() as isize;
-    }
-    operand = parse_twos_complement_int(operand, wide);
-    Ok(Mnemonic::MOV {
-        dest,
-        source: RegisterMemory::Immediate(operand as isize),
-    })
+        RegisterMemory::Immediate(*iter.next().unwrap())
+    };
+    Ok(Mnemonic::MOV { dest, source })
 }
 
 pub fn immediate_to_register<'a, I: Iterator<Item = &'a u8>>(
@@ -484,7 +554,7 @@ pub fn immediate_to_register<'a, I: Iterator<Item = &'a u8>>(
     mut iter: I,
 ) -> Result<Mnemonic> {
     let data_byte = iter.next().unwrap();
-    let mode = get_mode(&data_byte)?;
+    let mode = get_mode(data_byte)?;
     let rm = data_byte & 7;
     let byte = (data_byte >> 3) & 7;
     let immediate = ImmediateMode::try_from(byte)?;
@@ -495,34 +565,53 @@ pub fn immediate_to_register<'a, I: Iterator<Item = &'a u8>>(
         iter: &mut iter,
     };
     let dest = RegisterMemory::try_from(rm_encoding)?;
+    // a byte immediate with a wide destination is sign extended to 16 bits
     let im_encoding = ImmediateModeEncoding {
         dest,
         mode: immediate,
         iter,
-        wide: immediate_wide,
+        wide: register_wide,
+        sign_extend: register_wide && !immediate_wide,
     };
     let instruction = Mnemonic::try_from(im_encoding)?;
     Ok(instruction)
 }
 
 pub fn pop<'a, I: Iterator<Item = &'a u8>>(wide: bool, mut iter: I) -> Result<Mnemonic> {
-    Ok(Mnemonic::NOP)
+    let data_byte = iter.next().unwrap();
+    let mode = get_mode(data_byte)?;
+    let rm = data_byte & 7;
+    let rm_encoding = RegisterMemoryEncoding {
+        mode,
+        rm,
+        wide,
+        iter: &mut iter,
+    };
+    Ok(Mnemonic::POP(RegisterMemory::try_from(rm_encoding)?))
 }
 
 pub fn call<'a, I: Iterator<Item = &'a u8>>(mut iter: I) -> Result<Mnemonic> {
+    let (segment, offset) = far_pointer(iter.by_ref())?;
     Ok(Mnemonic::CALL {
-        far_proc: Some(0),
+        far_proc: Some((segment, offset)),
         near_proc: None,
     })
 }
 
+/// Reads an intersegment `offset, segment` operand pair and returns it as `(segment, offset)`
+pub fn far_pointer<'a, I: Iterator<Item = &'a u8>>(mut iter: I) -> Result<(u16, u16)> {
+    let offset = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+    let segment = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+    Ok((segment, offset))
+}
+
 pub fn logic_register_memory<'a, I: Iterator<Item = &'a u8>>(
     mut iter: I,
     wide: bool,
     source: RegisterMemory,
 ) -> Result<Mnemonic> {
     let data_byte = iter.next().unwrap();
-    let mode = get_mode(&data_byte)?;
+    let mode = get_mode(data_byte)?;
     let rm = data_byte & 7;
     let operation = (data_byte >> 3) & 7;
     let operator = LogicOperator::try_from(operation)?;
@@ -537,16 +626,122 @@ pub fn logic_register_memory<'a, I: Iterator<Item = &'a u8>>(
         operator,
         dest,
         source,
+        wide,
     }))
 }
 
+/// Decodes the TEST/NOT/NEG/MUL/IMUL/DIV/IDIV group, the multiply and divide destination is
+/// the implicit accumulator
+pub fn comparison_register_memory<'a, I: Iterator<Item = &'a u8>>(
+    mut iter: I,
+    wide: bool,
+) -> Result<Mnemonic> {
+    let data_byte = iter.next().unwrap();
+    let mode = get_mode(data_byte)?;
+    let rm = data_byte & 7;
+    let operator = ComparisonOperator::try_from((data_byte >> 3) & 7)?;
+    let rm_encoding = RegisterMemoryEncoding {
+        mode,
+        rm,
+        wide,
+        iter: &mut iter,
+    };
+    let operand = RegisterMemory::try_from(rm_encoding)?;
+    let accumulator = RegisterMemory::Register(if wide { Register::AX } else { Register::AL });
+    let mnemonic = match operator {
+        ComparisonOperator::TEST => {
+            let source = if wide {
+                let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+                RegisterMemory::ImmediateWide(data)
+            } else {
+                RegisterMemory::Immediate(*iter.next().unwrap())
+            };
+            Mnemonic::TEST {
+                dest: operand,
+                source,
+            }
+        }
+        ComparisonOperator::NOT => Mnemonic::NOT {
+            dest: operand,
+            wide,
+        },
+        ComparisonOperator::NEG => Mnemonic::NEG {
+            dest: operand,
+            wide,
+        },
+        ComparisonOperator::MUL => Mnemonic::MUL {
+            dest: accumulator,
+            source: operand,
+        },
+        ComparisonOperator::IMUL => Mnemonic::IMUL {
+            dest: accumulator,
+            source: operand,
+        },
+        ComparisonOperator::DIV => Mnemonic::DIV {
+            dest: accumulator,
+            source: operand,
+        },
+        ComparisonOperator::IDIV => Mnemonic::IDIV {
+            dest: accumulator,
+            source: operand,
+        },
+    };
+    Ok(mnemonic)
+}
+
+/// Decodes the INC/DEC/CALL/JMP/PUSH group, only INC and DEC come in a byte form
+pub fn group_register_memory<'a, I: Iterator<Item = &'a u8>>(
+    mut iter: I,
+    wide: bool,
+) -> Result<Mnemonic> {
+    let data_byte = iter.next().unwrap();
+    let mode = get_mode(data_byte)?;
+    let rm = data_byte & 7;
+    let operator = GroupOperator::try_from((data_byte >> 3) & 7)?;
+    let rm_encoding = RegisterMemoryEncoding {
+        mode,
+        rm,
+        wide,
+        iter: &mut iter,
+    };
+    let operand = RegisterMemory::try_from(rm_encoding)?;
+    let mnemonic = match operator {
+        GroupOperator::INC => Mnemonic::INC {
+            dest: operand,
+            wide,
+        },
+        GroupOperator::DEC => Mnemonic::DEC {
+            dest: operand,
+            wide,
+        },
+        GroupOperator::CALL => Mnemonic::CALLINDIRECT {
+            target: operand,
+            far: false,
+        },
+        GroupOperator::CALLFAR => Mnemonic::CALLINDIRECT {
+            target: operand,
+            far: true,
+        },
+        GroupOperator::JMP => Mnemonic::JMPINDIRECT {
+            target: operand,
+            far: false,
+        },
+        GroupOperator::JMPFAR => Mnemonic::JMPINDIRECT {
+            target: operand,
+            far: true,
+        },
+        GroupOperator::PUSH => Mnemonic::PUSH(operand),
+    };
+    Ok(mnemonic)
+}
+
 pub fn register_memory_segment<'a, I: Iterator<Item = &'a u8>>(
     wide: bool,
     mut iter: I,
     reversed: bool,
 ) -> Result<(RegisterMemory, RegisterMemory)> {
     let data_byte = iter.next().unwrap();
-    let mode = get_mode(&data_byte)?;
+    let mode = get_mode(data_byte)?;
     let rm = data_byte & 7;
     let (source, dest): (RegisterMemory, RegisterMemory);
     let segment = RegisterMemory::SegmentRegister(SegmentRegister::try_from((data_byte >> 3) & 7)?);
@@ -0,0 +1,186 @@
+use std::cell::RefCell;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+/// A peripheral on the I/O bus, it is handed the full port number so one device can decode
+/// several registers from the range it is attached to
+pub trait IoDevice {
+    fn read_byte(&mut self, port: u16) -> u8;
+    fn write_byte(&mut self, port: u16, value: u8);
+    /// A word access goes to `port` and `port + 1`, low byte first
+    fn read_word(&mut self, port: u16) -> u16 {
+        let low = self.read_byte(port);
+        let high = self.read_byte(port.wrapping_add(1));
+        u16::from_le_bytes([low, high])
+    }
+    fn write_word(&mut self, port: u16, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        self.write_byte(port, low);
+        self.write_byte(port.wrapping_add(1), high);
+    }
+    /// Lets timing dependent devices catch up with the `clocks` CPU cycles the last
+    /// instruction took
+    fn tick(&mut self, _clocks: u32) {}
+}
+
+/// Lets a device stay reachable after it has been attached, by keeping a clone of the `Rc`
+impl<T: IoDevice> IoDevice for Rc<RefCell<T>> {
+    fn read_byte(&mut self, port: u16) -> u8 {
+        self.borrow_mut().read_byte(port)
+    }
+    fn write_byte(&mut self, port: u16, value: u8) {
+        self.borrow_mut().write_byte(port, value)
+    }
+    fn read_word(&mut self, port: u16) -> u16 {
+        self.borrow_mut().read_word(port)
+    }
+    fn write_word(&mut self, port: u16, value: u16) {
+        self.borrow_mut().write_word(port, value)
+    }
+    fn tick(&mut self, clocks: u32) {
+        self.borrow_mut().tick(clocks)
+    }
+}
+
+/// What answers ports nothing is attached to: reads float high and every access is logged to
+/// stderr so missing peripherals are easy to spot
+#[derive(Debug, Default)]
+pub struct UnmappedPorts;
+
+impl IoDevice for UnmappedPorts {
+    fn read_byte(&mut self, port: u16) -> u8 {
+        eprintln!("IN from unmapped port 0x{:04x}", port);
+        0xff
+    }
+    fn write_byte(&mut self, port: u16, value: u8) {
+        eprintln!("OUT 0x{:02x} to unmapped port 0x{:04x}", value, port);
+    }
+}
+
+/// Routes IN and OUT to the device attached to the port, a word access goes to whichever
+/// device owns its first port
+pub struct IoBus {
+    devices: Vec<(RangeInclusive<u16>, Box<dyn IoDevice>)>,
+    unmapped: Box<dyn IoDevice>,
+}
+
+impl Default for IoBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for IoBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ranges: Vec<&RangeInclusive<u16>> =
+            self.devices.iter().map(|(ports, _)| ports).collect();
+        f.debug_struct("IoBus").field("devices", &ranges).finish()
+    }
+}
+
+impl IoBus {
+    pub fn new() -> Self {
+        Self {
+            devices: Vec::new(),
+            unmapped: Box::new(UnmappedPorts),
+        }
+    }
+
+    /// Attaches `device` to `ports`, where ranges overlap the device attached last wins
+    pub fn attach(&mut self, ports: RangeInclusive<u16>, device: impl IoDevice + 'static) {
+        self.devices.push((ports, Box::new(device)));
+    }
+
+    /// Replaces the `UnmappedPorts` default for ports no device is attached to
+    pub fn set_unmapped(&mut self, device: impl IoDevice + 'static) {
+        self.unmapped = Box::new(device);
+    }
+
+    pub fn read_byte(&mut self, port: u16) -> u8 {
+        self.device(port).read_byte(port)
+    }
+
+    pub fn write_byte(&mut self, port: u16, value: u8) {
+        self.device(port).write_byte(port, value)
+    }
+
+    pub fn read_word(&mut self, port: u16) -> u16 {
+        self.device(port).read_word(port)
+    }
+
+    pub fn write_word(&mut self, port: u16, value: u16) {
+        self.device(port).write_word(port, value)
+    }
+
+    /// Passes the time an instruction took on to every attached device
+    pub fn tick(&mut self, clocks: u32) {
+        for (_, device) in &mut self.devices {
+            device.tick(clocks);
+        }
+    }
+
+    fn device(&mut self, port: u16) -> &mut dyn IoDevice {
+        match self
+            .devices
+            .iter_mut()
+            .rev()
+            .find(|(ports, _)| ports.contains(&port))
+        {
+            Some((_, device)) => device.as_mut(),
+            None => self.unmapped.as_mut(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::{IoBus, IoDevice};
+
+    /// Remembers every access so the test can check where the bus sent it
+    #[derive(Default)]
+    struct Recorder {
+        writes: Vec<(u16, u8)>,
+        reads: Vec<u16>,
+    }
+
+    impl IoDevice for Recorder {
+        fn read_byte(&mut self, port: u16) -> u8 {
+            self.reads.push(port);
+            port as u8
+        }
+        fn write_byte(&mut self, port: u16, value: u8) {
+            self.writes.push((port, value));
+        }
+    }
+
+    #[test]
+    fn test_routing() {
+        let mut bus = IoBus::new();
+        let device = Rc::new(RefCell::new(Recorder::default()));
+        let unmapped = Rc::new(RefCell::new(Recorder::default()));
+        bus.attach(0x40..=0x43, Rc::clone(&device));
+        bus.set_unmapped(Rc::clone(&unmapped));
+        bus.write_byte(0x43, 0x36);
+        bus.write_word(0x40, 0x1234);
+        assert_eq!(bus.read_word(0x42), 0x4342);
+        bus.write_byte(0x61, 0x03);
+        assert_eq!(bus.read_byte(0x60), 0x60);
+        assert_eq!(
+            device.borrow().writes,
+            [(0x43, 0x36), (0x40, 0x34), (0x41, 0x12)]
+        );
+        assert_eq!(device.borrow().reads, [0x42, 0x43]);
+        assert_eq!(unmapped.borrow().writes, [(0x61, 0x03)]);
+        assert_eq!(unmapped.borrow().reads, [0x60]);
+
+        // a device attached later takes over the ports it overlaps
+        let replacement = Rc::new(RefCell::new(Recorder::default()));
+        bus.attach(0x43..=0x43, Rc::clone(&replacement));
+        bus.write_byte(0x43, 0x74);
+        assert_eq!(replacement.borrow().writes, [(0x43, 0x74)]);
+        assert_eq!(device.borrow().writes.len(), 3);
+    }
+}
@@ -1,27 +1,149 @@
-use computer_enhance::{cpu::Cpu, opcodes::OPCODE_TABLE, Result};
+use computer_enhance::{
+    clocks::Variant,
+    cpu::Cpu,
+    dos::{self, Dos},
+    opcodes::disassemble,
+    uart::{self, Uart},
+    xrefs::XrefTable,
+    Result,
+};
 use clap::Parser;
 
 #[derive(Parser, Debug)]
 struct Args {
     filename: String,
     #[arg(short, long)]
-    execute: bool
+    execute: bool,
+    /// Estimate the clocks each executed instruction takes on an 8086
+    #[arg(short, long)]
+    clocks: bool,
+    /// Whether clocks are estimated with the 8086 or the 8088 timings
+    #[arg(long, default_value = "8086", value_parser = ["8086", "8088"])]
+    cpu: String,
+    /// Annotate the listing with branch and memory cross references
+    #[arg(short, long)]
+    xrefs: bool,
+    /// Run the file as a DOS .COM or MZ .EXE program, it can only open files in its own directory
+    #[arg(short, long)]
+    dos: bool,
+    /// The command line passed to a DOS program
+    #[arg(long, default_value = "")]
+    arguments: String,
+    /// Where the COM1 serial port receives bytes from, `-` for stdin unless DOS is reading it
+    #[arg(long)]
+    serial_input: Option<String>,
+}
+
+/// Puts a UART on COM1 that transmits to stdout, it only receives anything with an input
+fn attach_serial(cpu: &mut Cpu, input: Option<&str>) -> Result<()> {
+    let input = match input {
+        Some("-") => uart::read_in_background(std::io::stdin()),
+        Some(path) => uart::read_in_background(std::fs::File::open(path)?),
+        None => std::sync::mpsc::channel().1,
+    };
+    let com1 = Uart::new(input, Box::new(std::io::stdout()));
+    cpu.io.attach(uart::COM1_PORTS, com1);
+    Ok(())
+}
+
+fn print_xrefs(binary: &[u8]) -> Result<()> {
+    let listing = disassemble(binary)?;
+    let xrefs = XrefTable::new(&listing);
+    for instruction in &listing {
+        for (from, kind) in xrefs.branches_to(instruction.offset) {
+            println!("; {:?} from 0x{:04x}", kind, from);
+        }
+        println!("0x{:04x}: {:?}", instruction.offset, instruction.mnemonic);
+        for &(address, access) in xrefs.memory_references_from(instruction.offset) {
+            let others: Vec<String> = xrefs
+                .memory_references(address)
+                .iter()
+                .filter(|(from, _)| *from != instruction.offset)
+                .map(|(from, access)| format!(", {:?} at 0x{:04x}", access, from))
+                .collect();
+            println!("; {:?} [0x{:04x}]{}", access, address, others.concat());
+        }
+    }
+    Ok(())
+}
+
+/// Runs a .COM or .EXE program until it terminates and returns its exit code
+fn run_dos(mut cpu: Cpu, path: &std::path::Path, arguments: &str) -> Result<u8> {
+    let binary = std::fs::read(path)?;
+    let root = path.parent().unwrap_or(std::path::Path::new("."));
+    let dos = Dos::new(
+        root,
+        Box::new(std::io::stdin().lock()),
+        Box::new(std::io::stdout()),
+    )
+    .install(&mut cpu);
+    let tail = if arguments.is_empty() {
+        String::new()
+    } else {
+        format!(" {}", arguments)
+    };
+    if binary.starts_with(b"MZ") {
+        dos::load_exe(&mut cpu, dos::PSP_SEGMENT, &binary, &tail)?;
+    } else {
+        dos::load_com(&mut cpu, dos::PSP_SEGMENT, &binary, &tail)?;
+    }
+    loop {
+        if let Some(exit_code) = dos.borrow().exit_code() {
+            return Ok(exit_code);
+        }
+        if cpu.halted() && !cpu.can_wake() {
+            return Err("The program halted without INT 21h/4Ch".into());
+        }
+        cpu.step()?;
+    }
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    // let args: Vec<String> = std::env::args().collect();
+    let mut cpu = Cpu::new();
+    if args.cpu == "8088" {
+        cpu.set_variant(Variant::I8088);
+    }
+    if args.dos {
+        // the UART would race DOS for every byte of the console input
+        if args.serial_input.as_deref() == Some("-") {
+            return Err("--serial-input - can't share stdin with a DOS program".into());
+        }
+        attach_serial(&mut cpu, args.serial_input.as_deref())?;
+        let path = std::path::Path::new(&args.filename);
+        let exit_code = run_dos(cpu, path, &args.arguments)?;
+        std::process::exit(exit_code as i32);
+    }
     let path = format!("{}/listings/part1/{}", env!("CARGO_MANIFEST_DIR"), args.filename);
     let binary = std::fs::read(path)?;
-    let mut cpu = Cpu::new();
-    let mut iter = binary.iter();
-    while let Some(byte) = iter.next() {
-        // println!("{:08b}", byte);
-        let opcode_fn = OPCODE_TABLE[*byte as usize];
-        let instruction = (opcode_fn)(&mut iter)?;
-        println!("{:?}", instruction);
-        if args.execute {
-            cpu.execute(instruction)?;
+    if args.xrefs {
+        return print_xrefs(&binary);
+    }
+    if args.execute {
+        attach_serial(&mut cpu, args.serial_input.as_deref())?;
+        cpu.load(&binary);
+        while (cpu.registers.ip as usize) < binary.len() && !cpu.halted() {
+            let instruction = cpu.step()?;
+            if !args.clocks {
+                println!("{:?}", instruction.mnemonic);
+                continue;
+            }
+            let clocks = cpu.last_clocks();
+            let breakdown = clocks
+                .breakdown()
+                .map(|breakdown| format!(" ({})", breakdown))
+                .unwrap_or_default();
+            println!(
+                "{:?} ; Clocks: +{} = {}{}",
+                instruction.mnemonic,
+                clocks.total(),
+                cpu.clocks(),
+                breakdown
+            );
+        }
+    } else {
+        for instruction in disassemble(&binary)? {
+            println!("{:?}", instruction.mnemonic);
         }
     }
     println!("{:?}", cpu.registers);
@@ -0,0 +1,94 @@
+/// The 8086 has 20 address lines, giving it a 1 MiB physical address space
+pub const MEMORY_SIZE: usize = 1 << 20;
+
+pub struct Memory {
+    bytes: Box<[u8]>,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for Memory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Memory").field("size", &self.bytes.len()).finish()
+    }
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self {
+            bytes: vec![0; MEMORY_SIZE].into_boxed_slice(),
+        }
+    }
+
+    /// Translates `segment:offset` into a physical address, addresses past the end of the
+    /// address space wrap around to the start just like they do on the 8086
+    pub fn physical_address(segment: u16, offset: u16) -> usize {
+        ((segment as usize) * 16 + offset as usize) % MEMORY_SIZE
+    }
+
+    pub fn read_byte(&self, segment: u16, offset: u16) -> u8 {
+        self.bytes[Self::physical_address(segment, offset)]
+    }
+
+    pub fn write_byte(&mut self, segment: u16, offset: u16, value: u8) {
+        self.bytes[Self::physical_address(segment, offset)] = value;
+    }
+
+    /// Reads a little-endian word, the high byte of a word at offset 0xFFFF comes from offset 0
+    /// of the same segment
+    pub fn read_word(&self, segment: u16, offset: u16) -> u16 {
+        u16::from_le_bytes([
+            self.read_byte(segment, offset),
+            self.read_byte(segment, offset.wrapping_add(1)),
+        ])
+    }
+
+    pub fn write_word(&mut self, segment: u16, offset: u16, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        self.write_byte(segment, offset, low);
+        self.write_byte(segment, offset.wrapping_add(1), high);
+    }
+
+    /// Copies `data` into memory starting at `segment:offset`
+    pub fn load(&mut self, segment: u16, offset: u16, data: &[u8]) {
+        for (i, byte) in data.iter().enumerate() {
+            let address = (Self::physical_address(segment, offset) + i) % MEMORY_SIZE;
+            self.bytes[address] = *byte;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Memory, MEMORY_SIZE};
+
+    #[test]
+    fn test_physical_address() {
+        assert_eq!(Memory::physical_address(0x1234, 0x0010), 0x12350);
+        assert_eq!(Memory::physical_address(0xffff, 0x0010), 0);
+        assert_eq!(Memory::physical_address(0xffff, 0xffff), 0x0ffef);
+        assert!(Memory::physical_address(0xffff, 0x000f) < MEMORY_SIZE);
+    }
+
+    #[test]
+    fn test_word_access() {
+        let mut memory = Memory::new();
+        memory.write_word(0x0100, 0x0002, 0xbeef);
+        assert_eq!(memory.read_byte(0x0100, 0x0002), 0xef);
+        assert_eq!(memory.read_byte(0x0100, 0x0003), 0xbe);
+        assert_eq!(memory.read_word(0x0000, 0x1002), 0xbeef);
+    }
+
+    #[test]
+    fn test_offset_wraparound() {
+        let mut memory = Memory::new();
+        memory.write_word(0x2000, 0xffff, 0x1234);
+        assert_eq!(memory.read_byte(0x2000, 0xffff), 0x34);
+        assert_eq!(memory.read_byte(0x2000, 0x0000), 0x12);
+        assert_eq!(memory.read_word(0x2000, 0xffff), 0x1234);
+    }
+}
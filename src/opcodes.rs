@@ -1,8 +1,7 @@
 use crate::{
     instructions::{
-        call, immediate_to_memory, immediate_to_register, jump, logic_register_memory, pop, register_memory_register, register_memory_segment, Mnemonic
+        call, comparison_register_memory, far_pointer, group_register_memory, immediate_to_memory, immediate_to_register, jump, logic_register_memory, pop, register_memory_register, register_memory_segment, Instruction, Mnemonic
     },
-    parse_twos_complement_int,
     registers::{Register, RegisterMemory, SegmentRegister},
     Result,
 };
@@ -32,21 +31,19 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // Add AL, Immediate8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
-        return Ok(Mnemonic::ADD {
+        let operand = *iter.next().unwrap();
+        Ok(Mnemonic::ADD {
             dest: RegisterMemory::Register(Register::AL),
             source: RegisterMemory::Immediate(operand),
-        });
+        })
     },
     // Add AX, Immediate16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
-        return Ok(Mnemonic::ADD {
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+        Ok(Mnemonic::ADD {
             dest: RegisterMemory::Register(Register::AX),
-            source: RegisterMemory::Immediate(operand),
-        });
+            source: RegisterMemory::ImmediateWide(operand),
+        })
     },
     // Push ES
     |_| Ok(Mnemonic::PUSHSEG(SegmentRegister::ES)),
@@ -69,26 +66,24 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // Or Reg16, Reg16/Mem16
     |iter| {
-        let (dest, source) = register_memory_register(true, iter, false)?;
+        let (dest, source) = register_memory_register(true, iter, true)?;
         Ok(Mnemonic::OR { dest, source })
     },
     // Or AL, Immediate8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
-        return Ok(Mnemonic::OR {
+        let operand = *iter.next().unwrap();
+        Ok(Mnemonic::OR {
             dest: RegisterMemory::Register(Register::AL),
             source: RegisterMemory::Immediate(operand),
-        });
+        })
     },
     // Or AX, Immediate16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
-        return Ok(Mnemonic::OR {
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+        Ok(Mnemonic::OR {
             dest: RegisterMemory::Register(Register::AX),
-            source: RegisterMemory::Immediate(operand),
-        });
+            source: RegisterMemory::ImmediateWide(operand),
+        })
     },
     // Push CS
     |_| Ok(Mnemonic::PUSHSEG(SegmentRegister::CS)),
@@ -115,21 +110,19 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // ADC AL, Immediate8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
-        return Ok(Mnemonic::ADC {
+        let operand = *iter.next().unwrap();
+        Ok(Mnemonic::ADC {
             dest: RegisterMemory::Register(Register::AL),
             source: RegisterMemory::Immediate(operand),
-        });
+        })
     },
     // ADC AX, Immediate16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
-        return Ok(Mnemonic::ADC {
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+        Ok(Mnemonic::ADC {
             dest: RegisterMemory::Register(Register::AX),
-            source: RegisterMemory::Immediate(operand),
-        });
+            source: RegisterMemory::ImmediateWide(operand),
+        })
     },
     // Push SS
     |_| Ok(Mnemonic::PUSHSEG(SegmentRegister::SS)),
@@ -156,21 +149,19 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // SBB AL, Immediate8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
-        return Ok(Mnemonic::SBB {
+        let operand = *iter.next().unwrap();
+        Ok(Mnemonic::SBB {
             dest: RegisterMemory::Register(Register::AL),
             source: RegisterMemory::Immediate(operand),
-        });
+        })
     },
     // SBB AX, Immediate16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
-        return Ok(Mnemonic::SBB {
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+        Ok(Mnemonic::SBB {
             dest: RegisterMemory::Register(Register::AX),
-            source: RegisterMemory::Immediate(operand),
-        });
+            source: RegisterMemory::ImmediateWide(operand),
+        })
     },
     // PUSH & POP DS
     |_| Ok(Mnemonic::PUSHSEG(SegmentRegister::DS)),
@@ -197,21 +188,19 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // AND AL, Immediate8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
-        return Ok(Mnemonic::AND {
+        let operand = *iter.next().unwrap();
+        Ok(Mnemonic::AND {
             dest: RegisterMemory::Register(Register::AL),
             source: RegisterMemory::Immediate(operand),
-        });
+        })
     },
     // AND AX, Immediate16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
-        return Ok(Mnemonic::AND {
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+        Ok(Mnemonic::AND {
             dest: RegisterMemory::Register(Register::AX),
-            source: RegisterMemory::Immediate(operand),
-        });
+            source: RegisterMemory::ImmediateWide(operand),
+        })
     },
     // segment override prefix (ES)
     |_| Ok(Mnemonic::SEGMENTOVERRIDE(SegmentRegister::ES)),
@@ -239,21 +228,19 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // Sub AL, Immediate8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
-        return Ok(Mnemonic::SUB {
+        let operand = *iter.next().unwrap();
+        Ok(Mnemonic::SUB {
             dest: RegisterMemory::Register(Register::AL),
             source: RegisterMemory::Immediate(operand),
-        });
+        })
     },
     // SUB AX, Immediate16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
-        return Ok(Mnemonic::SUB {
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+        Ok(Mnemonic::SUB {
             dest: RegisterMemory::Register(Register::AX),
-            source: RegisterMemory::Immediate(operand),
-        });
+            source: RegisterMemory::ImmediateWide(operand),
+        })
     },
     // segment override prefix (CS)
     |_| Ok(Mnemonic::SEGMENTOVERRIDE(SegmentRegister::CS)),
@@ -281,21 +268,19 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // XOR AL, Immediate8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
-        return Ok(Mnemonic::XOR {
+        let operand = *iter.next().unwrap();
+        Ok(Mnemonic::XOR {
             dest: RegisterMemory::Register(Register::AL),
             source: RegisterMemory::Immediate(operand),
-        });
+        })
     },
     // XOR AX, Immediate16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
-        return Ok(Mnemonic::XOR {
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+        Ok(Mnemonic::XOR {
             dest: RegisterMemory::Register(Register::AX),
-            source: RegisterMemory::Immediate(operand),
-        });
+            source: RegisterMemory::ImmediateWide(operand),
+        })
     },
     // segment override prefix (SS)
     |_| Ok(Mnemonic::SEGMENTOVERRIDE(SegmentRegister::SS)),
@@ -323,62 +308,140 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // CMP AL, Immediate8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
-        return Ok(Mnemonic::CMP {
+        let operand = *iter.next().unwrap();
+        Ok(Mnemonic::CMP {
             dest: RegisterMemory::Register(Register::AL),
             source: RegisterMemory::Immediate(operand),
-        });
+        })
     },
     // CMP AX, Immediate16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
-        return Ok(Mnemonic::CMP {
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+        Ok(Mnemonic::CMP {
             dest: RegisterMemory::Register(Register::AX),
-            source: RegisterMemory::Immediate(operand),
-        });
+            source: RegisterMemory::ImmediateWide(operand),
+        })
     },
     // segment override prefix (DS)
     |_| Ok(Mnemonic::SEGMENTOVERRIDE(SegmentRegister::DS)),
     // ascii adjust for subtract
     |_| Ok(Mnemonic::AAS),
     // Increment register
-    |_| Ok(Mnemonic::INC(Register::AX)),
-    |_| Ok(Mnemonic::INC(Register::CX)),
-    |_| Ok(Mnemonic::INC(Register::DX)),
-    |_| Ok(Mnemonic::INC(Register::BX)),
-    |_| Ok(Mnemonic::INC(Register::SP)),
-    |_| Ok(Mnemonic::INC(Register::BP)),
-    |_| Ok(Mnemonic::INC(Register::SI)),
-    |_| Ok(Mnemonic::INC(Register::DI)),
+    |_| {
+        Ok(Mnemonic::INC {
+            dest: RegisterMemory::Register(Register::AX),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::INC {
+            dest: RegisterMemory::Register(Register::CX),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::INC {
+            dest: RegisterMemory::Register(Register::DX),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::INC {
+            dest: RegisterMemory::Register(Register::BX),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::INC {
+            dest: RegisterMemory::Register(Register::SP),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::INC {
+            dest: RegisterMemory::Register(Register::BP),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::INC {
+            dest: RegisterMemory::Register(Register::SI),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::INC {
+            dest: RegisterMemory::Register(Register::DI),
+            wide: true,
+        })
+    },
     // Decrement register
-    |_| Ok(Mnemonic::DEC(Register::AX)),
-    |_| Ok(Mnemonic::DEC(Register::CX)),
-    |_| Ok(Mnemonic::DEC(Register::DX)),
-    |_| Ok(Mnemonic::DEC(Register::BX)),
-    |_| Ok(Mnemonic::DEC(Register::SP)),
-    |_| Ok(Mnemonic::DEC(Register::BP)),
-    |_| Ok(Mnemonic::DEC(Register::SI)),
-    |_| Ok(Mnemonic::DEC(Register::DI)),
+    |_| {
+        Ok(Mnemonic::DEC {
+            dest: RegisterMemory::Register(Register::AX),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::DEC {
+            dest: RegisterMemory::Register(Register::CX),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::DEC {
+            dest: RegisterMemory::Register(Register::DX),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::DEC {
+            dest: RegisterMemory::Register(Register::BX),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::DEC {
+            dest: RegisterMemory::Register(Register::SP),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::DEC {
+            dest: RegisterMemory::Register(Register::BP),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::DEC {
+            dest: RegisterMemory::Register(Register::SI),
+            wide: true,
+        })
+    },
+    |_| {
+        Ok(Mnemonic::DEC {
+            dest: RegisterMemory::Register(Register::DI),
+            wide: true,
+        })
+    },
     // Push to register
-    |_| Ok(Mnemonic::PUSH(Register::AX)),
-    |_| Ok(Mnemonic::PUSH(Register::CX)),
-    |_| Ok(Mnemonic::PUSH(Register::DX)),
-    |_| Ok(Mnemonic::PUSH(Register::BX)),
-    |_| Ok(Mnemonic::PUSH(Register::SP)),
-    |_| Ok(Mnemonic::PUSH(Register::BP)),
-    |_| Ok(Mnemonic::PUSH(Register::SI)),
-    |_| Ok(Mnemonic::PUSH(Register::DI)),
+    |_| Ok(Mnemonic::PUSH(RegisterMemory::Register(Register::AX))),
+    |_| Ok(Mnemonic::PUSH(RegisterMemory::Register(Register::CX))),
+    |_| Ok(Mnemonic::PUSH(RegisterMemory::Register(Register::DX))),
+    |_| Ok(Mnemonic::PUSH(RegisterMemory::Register(Register::BX))),
+    |_| Ok(Mnemonic::PUSH(RegisterMemory::Register(Register::SP))),
+    |_| Ok(Mnemonic::PUSH(RegisterMemory::Register(Register::BP))),
+    |_| Ok(Mnemonic::PUSH(RegisterMemory::Register(Register::SI))),
+    |_| Ok(Mnemonic::PUSH(RegisterMemory::Register(Register::DI))),
     // Pop from register
-    |_| Ok(Mnemonic::POP(Register::AX)),
-    |_| Ok(Mnemonic::POP(Register::CX)),
-    |_| Ok(Mnemonic::POP(Register::DX)),
-    |_| Ok(Mnemonic::POP(Register::BX)),
-    |_| Ok(Mnemonic::POP(Register::SP)),
-    |_| Ok(Mnemonic::POP(Register::BP)),
-    |_| Ok(Mnemonic::POP(Register::SI)),
-    |_| Ok(Mnemonic::POP(Register::DI)),
+    |_| Ok(Mnemonic::POP(RegisterMemory::Register(Register::AX))),
+    |_| Ok(Mnemonic::POP(RegisterMemory::Register(Register::CX))),
+    |_| Ok(Mnemonic::POP(RegisterMemory::Register(Register::DX))),
+    |_| Ok(Mnemonic::POP(RegisterMemory::Register(Register::BX))),
+    |_| Ok(Mnemonic::POP(RegisterMemory::Register(Register::SP))),
+    |_| Ok(Mnemonic::POP(RegisterMemory::Register(Register::BP))),
+    |_| Ok(Mnemonic::POP(RegisterMemory::Register(Register::SI))),
+    |_| Ok(Mnemonic::POP(RegisterMemory::Register(Register::DI))),
     |_| Ok(Mnemonic::NOP),
     |_| Ok(Mnemonic::NOP),
     |_| Ok(Mnemonic::NOP),
@@ -543,7 +606,7 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // LEA REG16,MEM16
     |iter| {
-        let (dest, source) = register_memory_register(true, iter, false)?;
+        let (dest, source) = register_memory_register(true, iter, true)?;
         Ok(Mnemonic::LEA { dest, source })
     },
     // MOV SEGREG, reg16/mem16
@@ -551,7 +614,7 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
         let (dest, source) = register_memory_segment(true, iter, true)?;
         Ok(Mnemonic::MOV { dest, source })
     },
-    // TODO: POP REG16/MEM16
+    // POP REG16/MEM16
     |iter| pop(true, iter),
     // Exchange AX, AX ??
     |_| Ok(Mnemonic::NOP),
@@ -609,37 +672,33 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     |_| Ok(Mnemonic::LAHF),
     // MOV AL, MEM8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
+        let address = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::AL),
-            source: RegisterMemory::Immediate(operand),
+            source: RegisterMemory::DirectAddress(address),
         })
     },
     // MOV AX, MEM16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
+        let address = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::AX),
-            source: RegisterMemory::Immediate(operand),
+            source: RegisterMemory::DirectAddress(address),
         })
     },
     // MOV MEM8, AL
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
+        let address = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::MOV {
-            dest: RegisterMemory::Immediate(operand),
+            dest: RegisterMemory::DirectAddress(address),
             source: RegisterMemory::Register(Register::AL),
         })
     },
     // MOV MEM16, AX
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
+        let address = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::MOV {
-            dest: RegisterMemory::Immediate(operand),
+            dest: RegisterMemory::DirectAddress(address),
             source: RegisterMemory::Register(Register::AX),
         })
     },
@@ -651,8 +710,7 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     |_| Ok(Mnemonic::CMPS { wide: true }),
     // TEST AL, MEM8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
+        let operand = *iter.next().unwrap();
         Ok(Mnemonic::TEST {
             dest: RegisterMemory::Register(Register::AL),
             source: RegisterMemory::Immediate(operand),
@@ -660,11 +718,10 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // TEST AX, MEM16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::TEST {
             dest: RegisterMemory::Register(Register::AX),
-            source: RegisterMemory::Immediate(operand),
+            source: RegisterMemory::ImmediateWide(operand),
         })
     },
     // TODO: STOS
@@ -678,8 +735,7 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     |_| Ok(Mnemonic::SCAS { wide: true }),
     // MOV AL, IMMED8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
+        let operand = *iter.next().unwrap();
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::AL),
             source: RegisterMemory::Immediate(operand),
@@ -687,8 +743,7 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // MOV CL, IMMED8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
+        let operand = *iter.next().unwrap();
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::CL),
             source: RegisterMemory::Immediate(operand),
@@ -696,8 +751,7 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // MOV DL, IMMED8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
+        let operand = *iter.next().unwrap();
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::DL),
             source: RegisterMemory::Immediate(operand),
@@ -705,8 +759,7 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // MOV BL, IMMED8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
+        let operand = *iter.next().unwrap();
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::BL),
             source: RegisterMemory::Immediate(operand),
@@ -714,8 +767,7 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // MOV AH, IMMED8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
+        let operand = *iter.next().unwrap();
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::AH),
             source: RegisterMemory::Immediate(operand),
@@ -723,8 +775,7 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // MOV CH, IMMED8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
+        let operand = *iter.next().unwrap();
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::CH),
             source: RegisterMemory::Immediate(operand),
@@ -732,8 +783,7 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // MOV DH, IMMED8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
+        let operand = *iter.next().unwrap();
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::DH),
             source: RegisterMemory::Immediate(operand),
@@ -741,8 +791,7 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // MOV BH, IMMED8
     |iter| {
-        let data = iter.next().unwrap();
-        let operand = parse_twos_complement_int(*data as isize, false);
+        let operand = *iter.next().unwrap();
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::BH),
             source: RegisterMemory::Immediate(operand),
@@ -750,82 +799,73 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     },
     // MOV AX, IMMED16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::AX),
-            source: RegisterMemory::Immediate(operand),
+            source: RegisterMemory::ImmediateWide(operand),
         })
     },
     // MOV CX, IMMED16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::CX),
-            source: RegisterMemory::Immediate(operand),
+            source: RegisterMemory::ImmediateWide(operand),
         })
     },
-    // TEST DX, IMMED16
+    // MOV DX, IMMED16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::DX),
-            source: RegisterMemory::Immediate(operand),
+            source: RegisterMemory::ImmediateWide(operand),
         })
     },
     // MOV BX, IMMED16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::BX),
-            source: RegisterMemory::Immediate(operand),
+            source: RegisterMemory::ImmediateWide(operand),
         })
     },
     // MOV SP, IMMED16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::SP),
-            source: RegisterMemory::Immediate(operand),
+            source: RegisterMemory::ImmediateWide(operand),
         })
     },
     // MOV BP, IMMED16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::BP),
-            source: RegisterMemory::Immediate(operand),
+            source: RegisterMemory::ImmediateWide(operand),
         })
     },
     // MOV SI, IMMED16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::SI),
-            source: RegisterMemory::Immediate(operand),
+            source: RegisterMemory::ImmediateWide(operand),
         })
     },
     // MOV DI, IMMED16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::MOV {
             dest: RegisterMemory::Register(Register::DI),
-            source: RegisterMemory::Immediate(operand),
+            source: RegisterMemory::ImmediateWide(operand),
         })
     },
     |_| Ok(Mnemonic::NOP),
     |_| Ok(Mnemonic::NOP),
     // RET IMMED16
     |iter| {
-        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
-        let operand = parse_twos_complement_int(data as isize, true);
+        let operand = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
         Ok(Mnemonic::RET {
             segment: Some(operand),
         })
@@ -834,12 +874,12 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     |_| Ok(Mnemonic::RET { segment: None }),
     // LES Reg16, Mem16
     |iter| {
-        let (dest, source) = register_memory_register(true, iter, false)?;
+        let (dest, source) = register_memory_register(true, iter, true)?;
         Ok(Mnemonic::LES { dest, source })
     },
     // LDS Reg16, Mem16
     |iter| {
-        let (dest, source) = register_memory_register(true, iter, false)?;
+        let (dest, source) = register_memory_register(true, iter, true)?;
         Ok(Mnemonic::LDS { dest, source })
     },
     // MOV MEM8, IMMED8
@@ -848,12 +888,18 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     |iter| immediate_to_memory(true, iter),
     |_| Ok(Mnemonic::NOP),
     |_| Ok(Mnemonic::NOP),
-    // TODO: RET, IMMED16(intersegment)
-    |iter| Ok(Mnemonic::RET { segment: Some(0) }),
-    |_| Ok(Mnemonic::RET { segment: None }),
+    // RET IMMED16 (intersegment)
+    |iter| {
+        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+        Ok(Mnemonic::RETF {
+            segment: Some(data),
+        })
+    },
+    // RET Intersegment
+    |_| Ok(Mnemonic::RETF { segment: None }),
     |_| Ok(Mnemonic::INT { value: 3 }),
     |iter| {
-        let value = *iter.next().unwrap() as isize;
+        let value = *iter.next().unwrap();
         Ok(Mnemonic::INT { value })
     },
     |_| Ok(Mnemonic::INTO),
@@ -867,8 +913,14 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     // SHR/SAR/ROL/ROR/RCL/RCR/SAL/SHL/SHR/SAR REG16/MEM16, CL
     |iter| logic_register_memory(iter, true, RegisterMemory::Register(Register::CL)),
     // 0xD4
-    |_| Ok(Mnemonic::AAM),
-    |_| Ok(Mnemonic::AAD),
+    |iter| {
+        let base = *iter.next().unwrap();
+        Ok(Mnemonic::AAM { base })
+    },
+    |iter| {
+        let base = *iter.next().unwrap();
+        Ok(Mnemonic::AAD { base })
+    },
     |_| Ok(Mnemonic::NOP),
     |_| Ok(Mnemonic::XLAT),
     // 0xD8
@@ -883,106 +935,104 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     // 0xDF
     |_| Ok(Mnemonic::ESC),
     |iter| {
-        return Ok(Mnemonic::LOOPNE {
-            short_label: *iter.next().unwrap() as isize,
-        });
+        Ok(Mnemonic::LOOPNE {
+            short_label: *iter.next().unwrap(),
+        })
     },
     |iter| {
-        return Ok(Mnemonic::LOOPE {
-            short_label: *iter.next().unwrap() as isize,
-        });
+        Ok(Mnemonic::LOOPE {
+            short_label: *iter.next().unwrap(),
+        })
     },
     |iter| {
-        return Ok(Mnemonic::LOOP {
-            short_label: *iter.next().unwrap() as isize,
-        });
+        Ok(Mnemonic::LOOP {
+            short_label: *iter.next().unwrap(),
+        })
     },
     // JCXZ
     |iter| {
-        return Ok(Mnemonic::JCXZ {
+        Ok(Mnemonic::JCXZ {
             label: *iter.next().unwrap(),
-        });
+        })
     },
     |iter| {
         let operand = *iter.next().unwrap();
-        return Ok(Mnemonic::IN {
+        Ok(Mnemonic::IN {
             dest: RegisterMemory::Register(Register::AL),
-            source: RegisterMemory::Immediate(operand as isize),
-        });
+            source: RegisterMemory::Immediate(operand),
+        })
     },
     |iter| {
         let operand = *iter.next().unwrap();
-        return Ok(Mnemonic::IN {
+        Ok(Mnemonic::IN {
             dest: RegisterMemory::Register(Register::AX),
-            source: RegisterMemory::Immediate(operand as isize),
-        });
+            source: RegisterMemory::Immediate(operand),
+        })
     },
     |iter| {
         let operand = *iter.next().unwrap();
-        return Ok(Mnemonic::OUT {
+        Ok(Mnemonic::OUT {
             dest: RegisterMemory::Register(Register::AL),
-            source: RegisterMemory::Immediate(operand as isize),
-        });
+            source: RegisterMemory::Immediate(operand),
+        })
     },
     |iter| {
         let operand = *iter.next().unwrap();
-        return Ok(Mnemonic::OUT {
+        Ok(Mnemonic::OUT {
             dest: RegisterMemory::Register(Register::AX),
-            source: RegisterMemory::Immediate(operand as isize),
-        });
+            source: RegisterMemory::Immediate(operand),
+        })
     },
     // CALL NEAR-PROC
     |iter| {
-        let operand = *iter.next().unwrap();
-        return Ok(Mnemonic::CALL {
+        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+        Ok(Mnemonic::CALL {
             far_proc: None,
-            near_proc: Some(operand as isize),
-        });
+            near_proc: Some(data as i16),
+        })
     },
-    // TODO: JMP NEAR-LABEL
+    // JMP NEAR-LABEL
     |iter| {
-        let operand = *iter.next().unwrap();
-        return Ok(Mnemonic::JMP {
-            label: operand as isize,
-        });
+        let data = u16::from_le_bytes([*iter.next().unwrap(), *iter.next().unwrap()]);
+        Ok(Mnemonic::JMP {
+            label: data as i16,
+        })
     },
-    // TODO: JMP FAR-LABEL
+    // JMP FAR-LABEL
     |iter| {
-        let operand = *iter.next().unwrap();
-        return Ok(Mnemonic::JMP {
-            label: operand as isize,
-        });
+        let (segment, offset) = far_pointer(iter)?;
+        Ok(Mnemonic::JMPFAR { segment, offset })
     },
-    // TODO: JMP SHORT-LABEL
+    // JMP SHORT-LABEL
     |iter| {
         let operand = *iter.next().unwrap();
-        return Ok(Mnemonic::JMP {
-            label: operand as isize,
-        });
+        Ok(Mnemonic::JMP {
+            label: operand as i8 as i16,
+        })
     },
     |_| {
-        return Ok(Mnemonic::IN {
+        Ok(Mnemonic::IN {
             dest: RegisterMemory::Register(Register::AL),
             source: RegisterMemory::Register(Register::DX),
-        });
+        })
     },
     |_| {
-        return Ok(Mnemonic::IN {
+        Ok(Mnemonic::IN {
             dest: RegisterMemory::Register(Register::AX),
             source: RegisterMemory::Register(Register::DX),
-        });
+        })
     },
     |_| {
-        return Ok(Mnemonic::OUT {
+        Ok(Mnemonic::OUT {
             dest: RegisterMemory::Register(Register::AL),
             source: RegisterMemory::Register(Register::DX),
-        });
+        })
     },
     |_| {
-        return Ok(Mnemonic::OUT {
+        Ok(Mnemonic::OUT {
             dest: RegisterMemory::Register(Register::AX),
             source: RegisterMemory::Register(Register::DX),
-        });
+        })
     },
     |_| Ok(Mnemonic::LOCK),
     |_| Ok(Mnemonic::NOP),
@@ -990,22 +1040,46 @@ pub const OPCODE_TABLE: [Thunk; 256] = [
     |_| Ok(Mnemonic::REP),
     |_| Ok(Mnemonic::HLT),
     |_| Ok(Mnemonic::CMC),
-    // TODO: TEST REG8/MEM8, IMMED8
-    |_| Ok(Mnemonic::NOP),
-    // TODO: TEST REG16/MEM16, IMMED16
-    |_| Ok(Mnemonic::NOP),
+    // TEST/NOT/NEG/MUL/IMUL/DIV/IDIV REG8/MEM8
+    |iter| comparison_register_memory(iter, false),
+    // TEST/NOT/NEG/MUL/IMUL/DIV/IDIV REG16/MEM16
+    |iter| comparison_register_memory(iter, true),
     |_| Ok(Mnemonic::CLC),
     |_| Ok(Mnemonic::STC),
     |_| Ok(Mnemonic::CLI),
     |_| Ok(Mnemonic::STI),
     |_| Ok(Mnemonic::CLD),
     |_| Ok(Mnemonic::STD),
-    // TODO: INC|DEC REG8/MEM8, INC|DEC MEM16
-    |_| Ok(Mnemonic::NOP),
-    // TODO: CALL|JMP|PUSH MEM16|REG16/MEM16
-    |_| Ok(Mnemonic::NOP),
+    // INC|DEC REG8/MEM8
+    |iter| group_register_memory(iter, false),
+    // INC|DEC|CALL|JMP|PUSH REG16/MEM16
+    |iter| group_register_memory(iter, true),
 ];
 
+/// Decodes the instruction at the start of `bytes`, returning it along with its encoded length
+pub fn decode(bytes: &[u8]) -> Result<(Mnemonic, usize)> {
+    let mut iter = bytes.iter();
+    let byte = iter.next().ok_or("Unexpected end of instruction stream")?;
+    let mnemonic = (OPCODE_TABLE[*byte as usize])(&mut iter)?;
+    Ok((mnemonic, bytes.len() - iter.as_slice().len()))
+}
+
+/// Decodes every instruction in a flat binary, keeping track of where each one starts
+pub fn disassemble(binary: &[u8]) -> Result<Vec<Instruction>> {
+    let mut listing = Vec::new();
+    let mut offset = 0;
+    while offset < binary.len() {
+        let (mnemonic, length) = decode(&binary[offset..])?;
+        listing.push(Instruction {
+            offset,
+            length,
+            mnemonic,
+        });
+        offset += length;
+    }
+    Ok(listing)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::registers::{RegisterMemory, SegmentRegister};
@@ -1019,8 +1093,9 @@ mod tests {
         assert_eq!(
             instruction,
             Mnemonic::RCL {
-                dest: RegisterMemory::Register(Register::SI),
-                source: RegisterMemory::Immediate(1)
+                dest: RegisterMemory::RegisterAddress(Register::SI),
+                source: RegisterMemory::Immediate(1),
+                wide: true,
             }
         );
     }
@@ -1042,7 +1117,7 @@ mod tests {
             instruction,
             Mnemonic::ADD {
                 dest: RegisterMemory::Register(Register::SI),
-                source: RegisterMemory::Immediate(2)
+                source: RegisterMemory::ImmediateWide(2)
             }
         );
     }
@@ -1056,7 +1131,7 @@ mod tests {
             instruction,
             Mnemonic::MOV {
                 dest: RegisterMemory::Register(Register::AX),
-                source: RegisterMemory::Immediate(2555)
+                source: RegisterMemory::DirectAddress(2555)
             }
         );
         let binary = [0b10100001, 0b00010000, 0b00000000];
@@ -1067,7 +1142,7 @@ mod tests {
             instruction,
             Mnemonic::MOV {
                 dest: RegisterMemory::Register(Register::AX),
-                source: RegisterMemory::Immediate(16)
+                source: RegisterMemory::DirectAddress(16)
             }
         );
     }
@@ -1135,7 +1210,7 @@ mod tests {
     }
     #[test]
     fn test_immediate_to_al() {
-        let binary = [0b10100000, 0b01000101];
+        let binary = [0b10100000, 0b01000101, 0b00000000];
         let mut iter = binary.iter();
         let byte = iter.next().unwrap();
         let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
@@ -1143,7 +1218,7 @@ mod tests {
             instruction,
             Mnemonic::MOV {
                 dest: RegisterMemory::Register(Register::AL),
-                source: RegisterMemory::Immediate(69)
+                source: RegisterMemory::DirectAddress(69)
             }
         )
     }
@@ -1157,7 +1232,7 @@ mod tests {
             instruction,
             Mnemonic::OR {
                 dest: RegisterMemory::Register(Register::CX),
-                source: RegisterMemory::Immediate(38)
+                source: RegisterMemory::ImmediateWide(38)
             }
         )
     }
@@ -1184,7 +1259,7 @@ mod tests {
         assert_eq!(
             instruction,
             Mnemonic::OR {
-                dest: RegisterMemory::Register(Register::SI),
+                dest: RegisterMemory::RegisterAddress(Register::SI),
                 source: RegisterMemory::Register(Register::AL),
             }
         );
@@ -1229,7 +1304,7 @@ mod tests {
             instruction,
             Mnemonic::ADD {
                 dest: RegisterMemory::Register(Register::AX),
-                source: RegisterMemory::Immediate(512),
+                source: RegisterMemory::ImmediateWide(512),
             }
         );
     }
@@ -1284,12 +1359,160 @@ mod tests {
         assert_eq!(
             instruction,
             Mnemonic::MOV {
+                dest: RegisterMemory::SegmentRegister(SegmentRegister::SS),
+                source: RegisterMemory::Register(Register::AX),
+            }
+        );
+    }
+    #[test]
+    fn test_mov_memory_immediate() {
+        let binary = [0b11000110, 0b01000110, 0b00000011, 0b11111111];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(
+            instruction,
+            Mnemonic::MOV {
+                dest: RegisterMemory::RegisterData(Register::BP, 3),
+                source: RegisterMemory::Immediate(0xff),
+            }
+        );
+        let binary = [0b11000111, 0b00000110, 0b11101000, 0b00000011, 0b00000001, 0b00000000];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(
+            instruction,
+            Mnemonic::MOV {
+                dest: RegisterMemory::DirectAddress(1000),
+                source: RegisterMemory::ImmediateWide(1),
+            }
+        );
+    }
+    #[test]
+    fn test_fixed_width_immediates() {
+        let binary = [0b10000001, 0b11000011, 0b01000000, 0b10011100];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(
+            instruction,
+            Mnemonic::ADD {
+                dest: RegisterMemory::Register(Register::BX),
+                source: RegisterMemory::ImmediateWide(40000),
+            }
+        );
+        let binary = [0b10000011, 0b11000001, 0b10100110];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(
+            instruction,
+            Mnemonic::ADD {
+                dest: RegisterMemory::Register(Register::CX),
+                source: RegisterMemory::ImmediateWide(-90i16 as u16),
+            }
+        );
+        let binary = [0b11101011, 0b10000000];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(instruction, Mnemonic::JMP { label: -128 });
+    }
+    #[test]
+    fn test_comparison_group() {
+        let binary = [0b11110110, 0b00000111, 0b00100010];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(
+            instruction,
+            Mnemonic::TEST {
+                dest: RegisterMemory::RegisterAddress(Register::BX),
+                source: RegisterMemory::Immediate(34),
+            }
+        );
+        let binary = [0b11110111, 0b00011110, 0b10000101, 0b00100100];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(
+            instruction,
+            Mnemonic::NEG {
+                dest: RegisterMemory::DirectAddress(9349),
+                wide: true,
+            }
+        );
+        let binary = [0b11110111, 0b11100011];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(
+            instruction,
+            Mnemonic::MUL {
                 dest: RegisterMemory::Register(Register::AX),
-                source: RegisterMemory::SegmentRegister(SegmentRegister::SS),
+                source: RegisterMemory::Register(Register::BX),
+            }
+        );
+    }
+    #[test]
+    fn test_group_register_memory() {
+        let binary = [0b11111110, 0b01000110, 0b00000000];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(
+            instruction,
+            Mnemonic::INC {
+                dest: RegisterMemory::RegisterData(Register::BP, 0),
+                wide: false,
+            }
+        );
+        let binary = [0b11111111, 0b00110110, 0b10111000, 0b00001011];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(
+            instruction,
+            Mnemonic::PUSH(RegisterMemory::DirectAddress(3000))
+        );
+        let binary = [0b11111111, 0b01011010, 0b11000110];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(
+            instruction,
+            Mnemonic::CALLINDIRECT {
+                target: RegisterMemory::CombineRegistersData(Register::BP, Register::SI, -0x3a),
+                far: true,
+            }
+        );
+        let binary = [0b11001010, 0b10010100, 0b01000100];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(
+            instruction,
+            Mnemonic::RETF {
+                segment: Some(17556)
             }
         );
     }
     #[test]
+    fn test_ascii_adjust_base() {
+        let binary = [0b11010100, 0b00001010];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(instruction, Mnemonic::AAM { base: 10 });
+        assert!(iter.next().is_none());
+        let binary = [0b11010101, 0b00010000];
+        let mut iter = binary.iter();
+        let byte = iter.next().unwrap();
+        let instruction = (OPCODE_TABLE[*byte as usize])(&mut iter).unwrap();
+        assert_eq!(instruction, Mnemonic::AAD { base: 16 });
+    }
+    #[test]
     fn test_add_register_memory_displacement_reverse() {
         let binary = [0b00000010, 0b01000000, 0b01000101];
         let mut iter = binary.iter();
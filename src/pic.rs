@@ -0,0 +1,194 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::cpu::Cpu;
+use crate::io::IoDevice;
+
+/// The command and data ports of the PC's 8259A
+pub const PIC_PORTS: std::ops::RangeInclusive<u16> = 0x20..=0x21;
+
+/// Which initialization command word the next write to the data port is
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Initialization {
+    Done,
+    VectorBase,
+    Cascade,
+    Mode,
+}
+
+/// An 8259A programmable interrupt controller in fixed priority mode, IRQ0 is the highest
+/// priority and a request is only delivered while no higher priority one is in service
+#[derive(Debug)]
+pub struct Pic {
+    /// Interrupt request register, the lines that have been raised but not acknowledged
+    requests: u8,
+    /// In-service register, the interrupts being handled that haven't had an EOI yet
+    in_service: u8,
+    mask: u8,
+    vector_base: u8,
+    initialization: Initialization,
+    single: bool,
+    needs_mode: bool,
+    auto_eoi: bool,
+    read_in_service: bool,
+}
+
+impl Default for Pic {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pic {
+    /// Starts out programmed the way the PC BIOS leaves it, IRQ0-7 on vectors 08h-0Fh
+    pub fn new() -> Self {
+        Self {
+            requests: 0,
+            in_service: 0,
+            mask: 0,
+            vector_base: 0x08,
+            initialization: Initialization::Done,
+            single: true,
+            needs_mode: true,
+            auto_eoi: false,
+            read_in_service: false,
+        }
+    }
+
+    /// Attaches a controller to the PIC ports of `cpu` and has the cpu take interrupts from it
+    pub fn attach(cpu: &mut Cpu) -> Rc<RefCell<Pic>> {
+        let pic = Rc::new(RefCell::new(Pic::new()));
+        cpu.io.attach(PIC_PORTS, Rc::clone(&pic));
+        cpu.connect_interrupt_controller(Rc::clone(&pic));
+        pic
+    }
+
+    /// Raises interrupt request line `irq`
+    pub fn request(&mut self, irq: u8) {
+        self.requests |= 1 << irq;
+    }
+
+    /// The highest priority request that is unmasked and not blocked by one in service
+    fn highest_pending(&self) -> Option<u8> {
+        let pending = self.requests & !self.mask;
+        let irq = pending.trailing_zeros() as u8;
+        let blocked_from = self.in_service.trailing_zeros() as u8;
+        (pending != 0 && irq < blocked_from).then_some(irq)
+    }
+
+    pub fn pending(&self) -> bool {
+        self.highest_pending().is_some()
+    }
+
+    /// The interrupt acknowledge cycle, moves the request in service and returns its vector
+    pub fn acknowledge(&mut self) -> Option<u8> {
+        let irq = self.highest_pending()?;
+        self.requests &= !(1 << irq);
+        if !self.auto_eoi {
+            self.in_service |= 1 << irq;
+        }
+        Some(self.vector_base + irq)
+    }
+
+    fn command(&mut self, value: u8) {
+        if value & 0x10 != 0 {
+            // ICW1 restarts initialization and clears everything it programmed
+            self.single = value & 0x02 != 0;
+            self.needs_mode = value & 0x01 != 0;
+            self.auto_eoi = false;
+            self.mask = 0;
+            self.in_service = 0;
+            self.requests = 0;
+            self.read_in_service = false;
+            self.initialization = Initialization::VectorBase;
+        } else if value & 0x08 != 0 {
+            // OCW3 selects which register a read of the command port returns
+            if value & 0x02 != 0 {
+                self.read_in_service = value & 0x01 != 0;
+            }
+        } else {
+            // OCW2, only the EOI commands matter without rotating priorities
+            match value >> 5 {
+                0b001 => self.in_service &= self.in_service.wrapping_sub(1),
+                0b011 => self.in_service &= !(1 << (value & 0x07)),
+                _ => (),
+            }
+        }
+    }
+
+    fn data(&mut self, value: u8) {
+        self.initialization = match self.initialization {
+            Initialization::Done => {
+                self.mask = value;
+                Initialization::Done
+            }
+            Initialization::VectorBase => {
+                self.vector_base = value & 0xf8;
+                if !self.single {
+                    Initialization::Cascade
+                } else if self.needs_mode {
+                    Initialization::Mode
+                } else {
+                    Initialization::Done
+                }
+            }
+            Initialization::Cascade if self.needs_mode => Initialization::Mode,
+            Initialization::Cascade => Initialization::Done,
+            Initialization::Mode => {
+                self.auto_eoi = value & 0x02 != 0;
+                Initialization::Done
+            }
+        };
+    }
+}
+
+impl IoDevice for Pic {
+    fn read_byte(&mut self, port: u16) -> u8 {
+        match port & 1 {
+            0 if self.read_in_service => self.in_service,
+            0 => self.requests,
+            _ => self.mask,
+        }
+    }
+    fn write_byte(&mut self, port: u16, value: u8) {
+        match port & 1 {
+            0 => self.command(value),
+            _ => self.data(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pic;
+    use crate::io::IoDevice;
+
+    #[test]
+    fn test_priorities() {
+        let mut pic = Pic::new();
+        // ICW1 edge triggered single with ICW4, vectors at 20h, 8086 mode; then mask IRQ3
+        for (port, value) in [(0x20, 0x13), (0x21, 0x20), (0x21, 0x01), (0x21, 0x08)] {
+            pic.write_byte(port, value);
+        }
+        assert_eq!(pic.read_byte(0x21), 0x08);
+        pic.request(3);
+        assert!(!pic.pending());
+        pic.request(4);
+        pic.request(1);
+        assert_eq!(pic.acknowledge(), Some(0x21));
+        // IRQ4 waits for the EOI of the higher priority IRQ1 but IRQ0 doesn't
+        assert_eq!(pic.acknowledge(), None);
+        pic.request(0);
+        assert_eq!(pic.acknowledge(), Some(0x20));
+        pic.write_byte(0x20, 0x0b);
+        assert_eq!(pic.read_byte(0x20), 0x03);
+        pic.write_byte(0x20, 0x20);
+        pic.write_byte(0x20, 0x20);
+        assert_eq!(pic.read_byte(0x20), 0x00);
+        assert_eq!(pic.acknowledge(), Some(0x24));
+        // unmasking lets the request that was held back through
+        pic.write_byte(0x20, 0x64);
+        pic.write_byte(0x21, 0x00);
+        assert_eq!(pic.acknowledge(), Some(0x23));
+    }
+}
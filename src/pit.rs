@@ -0,0 +1,285 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::cpu::Cpu;
+use crate::io::IoDevice;
+use crate::pic::Pic;
+
+/// The three counters and the mode register of the PC's 8253
+pub const PIT_PORTS: std::ops::RangeInclusive<u16> = 0x40..=0x43;
+
+/// The PIT runs off a 1.193182 MHz clock, a quarter of the PC's 4.77 MHz CPU clock
+const CPU_CLOCKS_PER_TICK: u32 = 4;
+
+/// Which bytes of the count a port access reads or writes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Access {
+    Low,
+    High,
+    LowHigh,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Counter {
+    mode: u8,
+    access: Access,
+    /// The count written by the program, 0 stands for 65536
+    reload: u16,
+    count: u32,
+    counting: bool,
+    output: bool,
+    latch: Option<u16>,
+    /// Whether the next byte of a low/high access is the high byte
+    write_high: bool,
+    read_high: bool,
+}
+
+impl Counter {
+    fn new() -> Self {
+        Self {
+            mode: 0,
+            access: Access::LowHigh,
+            reload: 0,
+            count: 0,
+            counting: false,
+            output: false,
+            latch: None,
+            write_high: false,
+            read_high: false,
+        }
+    }
+
+    fn initial_count(&self) -> u32 {
+        match self.reload {
+            0 => 0x10000,
+            reload => reload as u32,
+        }
+    }
+
+    fn load(&mut self) {
+        self.count = self.initial_count();
+        self.counting = true;
+        // mode 0 holds OUT low until the terminal count, the periodic modes start high
+        self.output = self.mode != 0;
+    }
+
+    /// Counts one input clock and returns whether OUT went from low to high. Modes 1 and 5
+    /// need a rising GATE to start, which nothing on the bus can provide, so they never count
+    fn clock(&mut self) -> bool {
+        if !self.counting {
+            return false;
+        }
+        match self.mode {
+            0 | 4 => {
+                self.count -= 1;
+                if self.count == 0 {
+                    self.counting = false;
+                    self.output = true;
+                    return self.mode == 0;
+                }
+            }
+            2 => {
+                self.count -= 1;
+                if self.count == 1 {
+                    self.output = false;
+                } else if self.count == 0 {
+                    self.count = self.initial_count();
+                    self.output = true;
+                    return true;
+                }
+            }
+            3 => {
+                // both halves of the square wave count down by two
+                self.count = self.count.saturating_sub(2);
+                if self.count == 0 {
+                    self.count = self.initial_count();
+                    self.output = !self.output;
+                    return self.output;
+                }
+            }
+            _ => (),
+        }
+        false
+    }
+
+    fn current(&self) -> u16 {
+        self.latch.unwrap_or(self.count as u16)
+    }
+
+    fn read(&mut self) -> u8 {
+        let [low, high] = self.current().to_le_bytes();
+        match self.access {
+            Access::Low => {
+                self.latch = None;
+                low
+            }
+            Access::High => {
+                self.latch = None;
+                high
+            }
+            Access::LowHigh if self.read_high => {
+                self.read_high = false;
+                self.latch = None;
+                high
+            }
+            Access::LowHigh => {
+                self.read_high = true;
+                low
+            }
+        }
+    }
+
+    fn write(&mut self, value: u8) {
+        match self.access {
+            Access::Low => self.reload = value as u16,
+            Access::High => self.reload = (value as u16) << 8,
+            Access::LowHigh if self.write_high => {
+                self.write_high = false;
+                self.reload = (self.reload & 0x00ff) | (value as u16) << 8;
+            }
+            Access::LowHigh => {
+                self.write_high = true;
+                self.reload = value as u16;
+                return;
+            }
+        }
+        self.load();
+    }
+}
+
+/// An 8253 programmable interval timer with counter 0 wired to IRQ0 of a `Pic`. The counters
+/// only count in binary, BCD counting isn't supported
+#[derive(Debug)]
+pub struct Pit {
+    counters: [Counter; 3],
+    /// CPU clocks that haven't added up to a whole timer tick yet
+    remainder: u32,
+    pic: Rc<RefCell<Pic>>,
+}
+
+impl Pit {
+    pub fn new(pic: Rc<RefCell<Pic>>) -> Self {
+        Self {
+            counters: [Counter::new(); 3],
+            remainder: 0,
+            pic,
+        }
+    }
+
+    /// Attaches a timer raising IRQ0 on `pic` to the PIT ports of `cpu`
+    pub fn attach(cpu: &mut Cpu, pic: Rc<RefCell<Pic>>) -> Rc<RefCell<Pit>> {
+        let pit = Rc::new(RefCell::new(Pit::new(pic)));
+        cpu.io.attach(PIT_PORTS, Rc::clone(&pit));
+        pit
+    }
+
+    /// The current count of `counter`, as a program would read it after latching it
+    pub fn count(&self, counter: usize) -> u16 {
+        self.counters[counter].count as u16
+    }
+
+    fn control(&mut self, value: u8) {
+        let select = (value >> 6) as usize;
+        if select == 3 {
+            return;
+        }
+        let counter = &mut self.counters[select];
+        let access = match (value >> 4) & 0x03 {
+            0 => {
+                counter.latch.get_or_insert(counter.count as u16);
+                return;
+            }
+            1 => Access::Low,
+            2 => Access::High,
+            _ => Access::LowHigh,
+        };
+        // modes 6 and 7 are the same as 2 and 3
+        let mode = match (value >> 1) & 0x07 {
+            mode @ 6..=7 => mode - 4,
+            mode => mode,
+        };
+        *counter = Counter {
+            mode,
+            access,
+            output: mode != 0,
+            ..Counter::new()
+        };
+    }
+}
+
+impl IoDevice for Pit {
+    fn read_byte(&mut self, port: u16) -> u8 {
+        match port & 0x03 {
+            3 => 0xff,
+            counter => self.counters[counter as usize].read(),
+        }
+    }
+    fn write_byte(&mut self, port: u16, value: u8) {
+        match port & 0x03 {
+            3 => self.control(value),
+            counter => self.counters[counter as usize].write(value),
+        }
+    }
+    fn tick(&mut self, clocks: u32) {
+        self.remainder += clocks;
+        while self.remainder >= CPU_CLOCKS_PER_TICK {
+            self.remainder -= CPU_CLOCKS_PER_TICK;
+            if self.counters[0].clock() {
+                self.pic.borrow_mut().request(0);
+            }
+            self.counters[1].clock();
+            self.counters[2].clock();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::Pit;
+    use crate::io::IoDevice;
+    use crate::pic::Pic;
+
+    #[test]
+    fn test_rate_generator() {
+        let pic = Rc::new(RefCell::new(Pic::new()));
+        let mut pit = Pit::new(Rc::clone(&pic));
+        // counter 0, low then high byte, mode 2, a period of 100 ticks
+        pit.write_byte(0x43, 0x34);
+        pit.write_byte(0x40, 100);
+        pit.write_byte(0x40, 0);
+        pit.tick(99 * 4 + 3);
+        assert!(!pic.borrow().pending());
+        assert_eq!(pit.count(0), 1);
+        pit.tick(1);
+        assert_eq!(pic.borrow_mut().acknowledge(), Some(0x08));
+        assert_eq!(pit.count(0), 100);
+        // latching freezes what the program reads while the counter keeps going
+        pit.tick(40);
+        pit.write_byte(0x43, 0x00);
+        pit.tick(40);
+        assert_eq!([pit.read_byte(0x40), pit.read_byte(0x40)], [90, 0]);
+        assert_eq!([pit.read_byte(0x40), pit.read_byte(0x40)], [80, 0]);
+    }
+
+    #[test]
+    fn test_terminal_count() {
+        let pic = Rc::new(RefCell::new(Pic::new()));
+        let mut pit = Pit::new(Rc::clone(&pic));
+        // counter 0, low byte only, mode 0 interrupts once after 10 ticks
+        pit.write_byte(0x43, 0x10);
+        pit.write_byte(0x40, 10);
+        pit.tick(10 * 4);
+        assert_eq!(pic.borrow_mut().acknowledge(), Some(0x08));
+        pic.borrow_mut().write_byte(0x20, 0x20);
+        pit.tick(1000);
+        assert!(!pic.borrow().pending());
+        // a square wave of 8 ticks raises IRQ0 once per period
+        pit.write_byte(0x43, 0x16);
+        pit.write_byte(0x40, 8);
+        pit.tick(8 * 4 * 3);
+        assert_eq!(pic.borrow_mut().acknowledge(), Some(0x08));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::cpu::CpuFlag;
+use crate::registers::Register;
+
+/// Bits of the FLAGS word that are hardwired to 1 on the 8086
+const RESERVED_FLAGS: u16 = 0xf002;
+
+const FLAG_BITS: [(CpuFlag, u16); 9] = [
+    (CpuFlag::CF, 1 << 0),
+    (CpuFlag::PF, 1 << 2),
+    (CpuFlag::AF, 1 << 4),
+    (CpuFlag::ZF, 1 << 6),
+    (CpuFlag::SF, 1 << 7),
+    (CpuFlag::TF, 1 << 8),
+    (CpuFlag::IF, 1 << 9),
+    (CpuFlag::DF, 1 << 10),
+    (CpuFlag::OF, 1 << 11),
+];
+
+impl CpuFlag {
+    /// The bit this flag occupies in the packed FLAGS word
+    pub fn mask(&self) -> u16 {
+        FLAG_BITS
+            .iter()
+            .find(|(flag, _)| flag == self)
+            .map(|(_, mask)| *mask)
+            .unwrap()
+    }
+}
+
+/// The 8086 general purpose registers, stored as eight words in encoding order
+/// (AX, CX, DX, BX, SP, BP, SI, DI). The byte registers AL..BL and AH..BH are views onto the
+/// low and high halves of the first four words, so writing AH is visible through AX.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RegisterFile {
+    words: [u16; 8],
+    pub ip: u16,
+    flags: u16,
+}
+
+impl Default for RegisterFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RegisterFile {
+    pub fn new() -> Self {
+        Self {
+            words: [0; 8],
+            ip: 0,
+            flags: RESERVED_FLAGS,
+        }
+    }
+
+    pub fn get(&self, register: Register) -> u16 {
+        let index = register as usize;
+        match index {
+            0..=3 => self.words[index] & 0xff,
+            4..=7 => self.words[index - 4] >> 8,
+            _ => self.words[index - 8],
+        }
+    }
+
+    /// Writes a register, byte registers only take the low 8 bits of `value`
+    pub fn set(&mut self, register: Register, value: u16) {
+        let index = register as usize;
+        match index {
+            0..=3 => self.words[index] = (self.words[index] & 0xff00) | (value & 0xff),
+            4..=7 => {
+                self.words[index - 4] = (self.words[index - 4] & 0x00ff) | ((value & 0xff) << 8)
+            }
+            _ => self.words[index - 8] = value,
+        }
+    }
+
+    pub fn flag(&self, flag: CpuFlag) -> bool {
+        self.flags & flag.mask() != 0
+    }
+
+    pub fn set_flag(&mut self, flag: CpuFlag, value: bool) {
+        if value {
+            self.flags |= flag.mask();
+        } else {
+            self.flags &= !flag.mask();
+        }
+    }
+
+    /// The packed FLAGS word as PUSHF stores it
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
+    pub fn set_flags(&mut self, value: u16) {
+        let defined = FLAG_BITS.iter().fold(0, |mask, (_, bit)| mask | bit);
+        self.flags = (value & defined) | RESERVED_FLAGS;
+    }
+}
+
+const WIDE_REGISTERS: [(&str, Register); 8] = [
+    ("ax", Register::AX),
+    ("cx", Register::CX),
+    ("dx", Register::DX),
+    ("bx", Register::BX),
+    ("sp", Register::SP),
+    ("bp", Register::BP),
+    ("si", Register::SI),
+    ("di", Register::DI),
+];
+
+impl std::fmt::Debug for RegisterFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut registers = f.debug_struct("RegisterFile");
+        for (name, register) in WIDE_REGISTERS {
+            registers.field(name, &format_args!("{:#06x}", self.get(register)));
+        }
+        registers
+            .field("ip", &format_args!("{:#06x}", self.ip))
+            .field("flags", &format_args!("{:#06x}", self.flags))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RegisterFile;
+    use crate::cpu::CpuFlag;
+    use crate::registers::Register;
+
+    #[test]
+    fn test_byte_registers_alias_words() {
+        let mut registers = RegisterFile::new();
+        registers.set(Register::AX, 0x1234);
+        assert_eq!(registers.get(Register::AL), 0x34);
+        assert_eq!(registers.get(Register::AH), 0x12);
+        registers.set(Register::AH, 0x01);
+        assert_eq!(registers.get(Register::AX), 0x0134);
+        registers.set(Register::BL, 0x1ff);
+        assert_eq!(registers.get(Register::BX), 0x00ff);
+        registers.set(Register::DH, 0xab);
+        assert_eq!(registers.get(Register::DX), 0xab00);
+        assert_eq!(registers.get(Register::DL), 0);
+    }
+
+    #[test]
+    fn test_packed_flags() {
+        let mut registers = RegisterFile::new();
+        registers.set_flag(CpuFlag::CF, true);
+        registers.set_flag(CpuFlag::ZF, true);
+        assert_eq!(registers.flags(), 0xf043);
+        registers.set_flags(0x0800);
+        assert!(registers.flag(CpuFlag::OF));
+        assert!(!registers.flag(CpuFlag::CF));
+        assert_eq!(registers.flags(), 0xf802);
+    }
+}
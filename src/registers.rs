@@ -1,14 +1,14 @@
-use crate::{parse_twos_complement_int, Result};
+use crate::Result;
 
 const MEMORY_MODE_ENCODING: [RegisterMemory; 8] = [
     RegisterMemory::CombineRegisters(Register::BX, Register::SI),
     RegisterMemory::CombineRegisters(Register::BX, Register::DI),
     RegisterMemory::CombineRegisters(Register::BP, Register::SI),
     RegisterMemory::CombineRegisters(Register::BP, Register::DI),
-    RegisterMemory::Register(Register::SI),
-    RegisterMemory::Register(Register::DI),
+    RegisterMemory::RegisterAddress(Register::SI),
+    RegisterMemory::RegisterAddress(Register::DI),
     RegisterMemory::DirectAddress(0),
-    RegisterMemory::Register(Register::BX),
+    RegisterMemory::RegisterAddress(Register::BX),
 ];
 
 const MEMORY_MODE_DISPLACEMENT_ENCODING: [RegisterMemory; 8] = [
@@ -84,17 +84,68 @@ pub struct RegisterMemoryEncoding<I> {
     pub iter: I,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum RegisterMemory {
     SegmentRegister(SegmentRegister),
     Register(Register),
+    RegisterAddress(Register),
     CombineRegisters(Register, Register),
-    DirectAddress(isize),
-    RegisterData(Register, isize),
-    RegisterDataWide(Register, isize),
-    CombineRegistersData(Register, Register, isize),
-    CombineRegistersDataWide(Register, Register, isize),
-    Immediate(isize),
+    DirectAddress(u16),
+    RegisterData(Register, i8),
+    RegisterDataWide(Register, i16),
+    CombineRegistersData(Register, Register, i8),
+    CombineRegistersDataWide(Register, Register, i16),
+    Immediate(u8),
+    ImmediateWide(u16),
+}
+
+impl RegisterMemory {
+    pub fn is_memory(&self) -> bool {
+        !matches!(
+            self,
+            Self::Register(_)
+                | Self::SegmentRegister(_)
+                | Self::Immediate(_)
+                | Self::ImmediateWide(_)
+        )
+    }
+
+    /// The operand width implied by the operand itself, memory operands take their width from
+    /// the other operand or the instruction
+    pub fn is_wide(&self) -> Option<bool> {
+        match self {
+            Self::Register(register) => Some(register.is_wide()),
+            Self::SegmentRegister(_) | Self::ImmediateWide(_) => Some(true),
+            Self::Immediate(_) => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Registers used to compute the effective address of a memory operand
+    pub fn address_registers(&self) -> Vec<Register> {
+        match *self {
+            Self::RegisterAddress(register)
+            | Self::RegisterData(register, _)
+            | Self::RegisterDataWide(register, _) => vec![register],
+            Self::CombineRegisters(base, index)
+            | Self::CombineRegistersData(base, index, _)
+            | Self::CombineRegistersDataWide(base, index, _) => vec![base, index],
+            _ => vec![],
+        }
+    }
+
+    /// The segment a memory operand is relative to when there is no override prefix,
+    /// BP based addressing uses the stack segment and everything else the data segment
+    pub fn default_segment(&self) -> Option<SegmentRegister> {
+        if !self.is_memory() {
+            return None;
+        }
+        if self.address_registers().first() == Some(&Register::BP) {
+            Some(SegmentRegister::SS)
+        } else {
+            Some(SegmentRegister::DS)
+        }
+    }
 }
 
 impl<'a, I: Iterator<Item = &'a u8>> TryFrom<RegisterMemoryEncoding<I>> for RegisterMemory {
@@ -105,31 +156,25 @@ impl<'a, I: Iterator<Item = &'a u8>> TryFrom<RegisterMemoryEncoding<I>> for Regi
                 let memory_mode = MEMORY_MODE_ENCODING[value.rm as usize];
                 match memory_mode {
                     Self::DirectAddress(_) => {
-                        let mut operand: isize;
-                        if value.wide {
-                            operand = u16::from_le_bytes([
-                                *value.iter.next().unwrap(),
-                                *value.iter.next().unwrap(),
-                            ]) as isize;
-                        } else {
-                            operand = *value.iter.next().unwrap() as isize;
-                        }
-                        operand = parse_twos_complement_int(operand, value.wide);
+                        // direct addresses are always 16 bits regardless of the operand width
+                        let operand = u16::from_le_bytes([
+                            *value.iter.next().unwrap(),
+                            *value.iter.next().unwrap(),
+                        ]);
                         Ok(Self::DirectAddress(operand))
                     }
                     _ => Ok(memory_mode),
                 }
             }
             Mode::MemoryModeDisplacement => {
-                let displacement =
-                    parse_twos_complement_int(*value.iter.next().unwrap() as isize, false);
+                let displacement = *value.iter.next().unwrap() as i8;
                 let register_memory = MEMORY_MODE_DISPLACEMENT_ENCODING[value.rm as usize];
                 match register_memory {
                     Self::RegisterData(dest, _) => {
-                        return Ok(Self::RegisterData(dest, displacement));
+                        Ok(Self::RegisterData(dest, displacement))
                     }
                     Self::CombineRegistersData(dest, source, _) => {
-                        return Ok(Self::CombineRegistersData(dest, source, displacement));
+                        Ok(Self::CombineRegistersData(dest, source, displacement))
                     }
                     _ => Err("Failed to parse displacement".into()),
                 }
@@ -137,23 +182,23 @@ impl<'a, I: Iterator<Item = &'a u8>> TryFrom<RegisterMemoryEncoding<I>> for Regi
             Mode::MemoryModeDisplacementWide => {
                 let data =
                     u16::from_le_bytes([*value.iter.next().unwrap(), *value.iter.next().unwrap()]);
-                let displacement = parse_twos_complement_int(data as isize, true);
+                let displacement = data as i16;
                 let register_memory = MEMORY_MODE_DISPLACEMENT_WIDE_ENCODING[value.rm as usize];
                 match register_memory {
                     Self::RegisterDataWide(dest, _) => {
-                        return Ok(Self::RegisterDataWide(dest, displacement));
+                        Ok(Self::RegisterDataWide(dest, displacement))
                     }
                     Self::CombineRegistersDataWide(dest, source, _) => {
-                        return Ok(Self::CombineRegistersDataWide(dest, source, displacement));
+                        Ok(Self::CombineRegistersDataWide(dest, source, displacement))
                     }
                     _ => Err("Failed to parse wide displacement".into()),
                 }
             }
             Mode::RegisterMode => {
                 if value.wide {
-                    return Ok(RegisterMemory::Register(WIDE_REGISTERS[value.rm as usize]));
+                    Ok(RegisterMemory::Register(WIDE_REGISTERS[value.rm as usize]))
                 } else {
-                    return Ok(RegisterMemory::Register(REGISTERS[value.rm as usize]));
+                    Ok(RegisterMemory::Register(REGISTERS[value.rm as usize]))
                 }
             }
         }
@@ -203,6 +248,15 @@ pub enum Register {
     DI,
 }
 
+impl Register {
+    pub fn is_wide(&self) -> bool {
+        !matches!(
+            self,
+            Self::AL | Self::CL | Self::DL | Self::BL | Self::AH | Self::CH | Self::DH | Self::BH
+        )
+    }
+}
+
 pub struct RegisterEncoding {
     pub byte: u8,
     pub wide: bool,
@@ -0,0 +1,222 @@
+use std::io::{Read, Write};
+use std::sync::mpsc::{channel, Receiver};
+
+use crate::io::IoDevice;
+
+/// The registers of the first serial port on a PC
+pub const COM1_PORTS: std::ops::RangeInclusive<u16> = 0x3f8..=0x3ff;
+
+/// Line status bits: a received byte is waiting, the transmit holding register is empty and
+/// the transmitter has nothing left to shift out
+const DATA_READY: u8 = 0x01;
+const TRANSMIT_HOLDING_EMPTY: u8 = 0x20;
+const TRANSMITTER_EMPTY: u8 = 0x40;
+
+const DIVISOR_LATCH_ACCESS: u8 = 0x80;
+const LOOPBACK: u8 = 0x10;
+
+/// Reads `input` from a background thread, so a program polling the line status never blocks
+/// the simulator while it waits for stdin
+pub fn read_in_background(mut input: impl Read + Send + 'static) -> Receiver<u8> {
+    let (sender, receiver) = channel();
+    std::thread::spawn(move || {
+        let mut byte = [0];
+        while let Ok(1) = input.read(&mut byte) {
+            if sender.send(byte[0]).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// An 8250 UART without interrupts. Bytes written to the transmit holding register go out
+/// straight away so the transmitter always reads as empty, and received bytes are taken from
+/// `input` one at a time as the program reads them
+pub struct Uart {
+    input: Receiver<u8>,
+    output: Box<dyn Write>,
+    received: Option<u8>,
+    divisor: u16,
+    interrupt_enable: u8,
+    line_control: u8,
+    modem_control: u8,
+    scratch: u8,
+}
+
+impl std::fmt::Debug for Uart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Uart")
+            .field("received", &self.received)
+            .field("divisor", &self.divisor)
+            .field("line_control", &self.line_control)
+            .field("modem_control", &self.modem_control)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Uart {
+    pub fn new(input: Receiver<u8>, output: Box<dyn Write>) -> Self {
+        Self {
+            input,
+            output,
+            received: None,
+            divisor: 0,
+            interrupt_enable: 0,
+            line_control: 0,
+            modem_control: 0,
+            scratch: 0,
+        }
+    }
+
+    fn divisor_latch(&self) -> bool {
+        self.line_control & DIVISOR_LATCH_ACCESS != 0
+    }
+
+    fn data_ready(&mut self) -> bool {
+        if self.received.is_none() {
+            self.received = self.input.try_recv().ok();
+        }
+        self.received.is_some()
+    }
+
+    fn line_status(&mut self) -> u8 {
+        let ready = if self.data_ready() { DATA_READY } else { 0 };
+        ready | TRANSMIT_HOLDING_EMPTY | TRANSMITTER_EMPTY
+    }
+
+    fn transmit(&mut self, value: u8) {
+        if self.modem_control & LOOPBACK != 0 {
+            self.received = Some(value);
+            return;
+        }
+        // a UART has no way to report that the host side went away, the byte is just lost
+        let _ = self
+            .output
+            .write_all(&[value])
+            .and_then(|_| self.output.flush());
+    }
+}
+
+impl IoDevice for Uart {
+    fn read_byte(&mut self, port: u16) -> u8 {
+        match port & 0x07 {
+            0 if self.divisor_latch() => self.divisor as u8,
+            0 => {
+                self.data_ready();
+                self.received.take().unwrap_or(0)
+            }
+            1 if self.divisor_latch() => (self.divisor >> 8) as u8,
+            1 => self.interrupt_enable,
+            // no interrupt is ever pending
+            2 => 0x01,
+            3 => self.line_control,
+            4 => self.modem_control,
+            5 => self.line_status(),
+            // in loopback RTS comes back as CTS, DTR as DSR and OUT1 and OUT2 as RI and DCD,
+            // otherwise the other end is always ready
+            6 if self.modem_control & LOOPBACK != 0 => {
+                let control = self.modem_control;
+                ((control & 0x01) << 5) | ((control & 0x02) << 3) | ((control & 0x0c) << 4)
+            }
+            6 => 0xb0,
+            _ => self.scratch,
+        }
+    }
+    fn write_byte(&mut self, port: u16, value: u8) {
+        match port & 0x07 {
+            0 if self.divisor_latch() => self.divisor = (self.divisor & 0xff00) | value as u16,
+            0 => self.transmit(value),
+            1 if self.divisor_latch() => {
+                self.divisor = (self.divisor & 0x00ff) | (value as u16) << 8
+            }
+            1 => self.interrupt_enable = value & 0x0f,
+            3 => self.line_control = value,
+            4 => self.modem_control = value & 0x1f,
+            7 => self.scratch = value,
+            // the interrupt identification and status registers are read only
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::rc::Rc;
+    use std::sync::mpsc::channel;
+
+    use super::{Uart, COM1_PORTS};
+    use crate::cpu::Cpu;
+    use crate::io::IoDevice;
+    use crate::registers::Register;
+
+    #[derive(Clone, Default)]
+    struct Terminal(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Terminal {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_echo_program() {
+        let (sender, receiver) = channel();
+        let terminal = Terminal::default();
+        let mut cpu = Cpu::new();
+        cpu.io
+            .attach(COM1_PORTS, Uart::new(receiver, Box::new(terminal.clone())));
+        // mov dx, 0x3fd; wait: in al, dx; test al, 1; jz wait; mov dx, 0x3f8; in al, dx;
+        // inc ax; out dx, al
+        let program = [
+            0xba, 0xfd, 0x03, 0xec, 0xa8, 0x01, 0x74, 0xfb, 0xba, 0xf8, 0x03, 0xec, 0x40, 0xee,
+        ];
+        cpu.load(&program);
+        for _ in 0..10 {
+            cpu.step().unwrap();
+        }
+        assert_eq!(cpu.registers.ip, 0x0003);
+        sender.send(b'H').unwrap();
+        while (cpu.registers.ip as usize) < program.len() {
+            cpu.step().unwrap();
+        }
+        assert_eq!(cpu.registers.get(Register::AL), b'I' as u16);
+        assert_eq!(terminal.0.borrow().as_slice(), b"I");
+    }
+
+    #[test]
+    fn test_registers() {
+        let (sender, receiver) = channel();
+        let terminal = Terminal::default();
+        let mut uart = Uart::new(receiver, Box::new(terminal.clone()));
+        assert_eq!(uart.read_byte(0x3fd), 0x60);
+        // the divisor latch shares its ports with the data and interrupt enable registers
+        uart.write_byte(0x3fb, 0x83);
+        uart.write_byte(0x3f8, 0x0c);
+        uart.write_byte(0x3f9, 0x00);
+        uart.write_byte(0x3fb, 0x03);
+        assert_eq!(uart.divisor, 12);
+        uart.write_byte(0x3f8, b'A');
+        assert_eq!(terminal.0.borrow().as_slice(), b"A");
+        sender.send(b'z').unwrap();
+        assert_eq!(uart.read_byte(0x3fd), 0x61);
+        assert_eq!(uart.read_byte(0x3f8), b'z');
+        assert_eq!(uart.read_byte(0x3fd), 0x60);
+        // loopback sends the byte back to the receiver instead of the host
+        uart.write_byte(0x3fc, 0x10);
+        uart.write_byte(0x3f8, b'B');
+        assert_eq!(uart.read_byte(0x3f8), b'B');
+        assert_eq!(terminal.0.borrow().as_slice(), b"A");
+        // DTR loops back to DSR and RTS to CTS
+        uart.write_byte(0x3fc, 0x11);
+        assert_eq!(uart.read_byte(0x3fe), 0x20);
+        uart.write_byte(0x3fc, 0x12);
+        assert_eq!(uart.read_byte(0x3fe), 0x10);
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+
+use crate::dataflow::Location;
+use crate::instructions::{Instruction, Mnemonic};
+use crate::registers::RegisterMemory;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BranchKind {
+    Jump,
+    Call,
+    Loop,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MemoryAccess {
+    Read,
+    Write,
+}
+
+/// Cross references for a disassembled binary.
+///
+/// `branches` maps a target offset to every instruction that jumps, calls or loops to it.
+/// `memory` maps a direct address like `[1000]` to every instruction that reads or writes it,
+/// and `memory_from` is the same references indexed by the instruction's offset. Operands with
+/// a base or index register like `[bx+1000]` aren't constant so they are left out.
+#[derive(Debug, Default)]
+pub struct XrefTable {
+    pub branches: BTreeMap<usize, Vec<(usize, BranchKind)>>,
+    pub memory: BTreeMap<u16, Vec<(usize, MemoryAccess)>>,
+    pub memory_from: BTreeMap<usize, Vec<(u16, MemoryAccess)>>,
+}
+
+impl XrefTable {
+    pub fn new(listing: &[Instruction]) -> Self {
+        let mut table = Self::default();
+        for instruction in listing {
+            if let Some((target, kind)) = branch_target(instruction) {
+                table
+                    .branches
+                    .entry(target)
+                    .or_default()
+                    .push((instruction.offset, kind));
+            }
+            for (operand, access) in memory_operands(&instruction.mnemonic) {
+                if let RegisterMemory::DirectAddress(address) = operand {
+                    table
+                        .memory
+                        .entry(address)
+                        .or_default()
+                        .push((instruction.offset, access));
+                    table
+                        .memory_from
+                        .entry(instruction.offset)
+                        .or_default()
+                        .push((address, access));
+                }
+            }
+        }
+        table
+    }
+
+    pub fn branches_to(&self, offset: usize) -> &[(usize, BranchKind)] {
+        self.branches.get(&offset).map_or(&[], |xrefs| xrefs.as_slice())
+    }
+
+    pub fn memory_references(&self, address: u16) -> &[(usize, MemoryAccess)] {
+        self.memory.get(&address).map_or(&[], |xrefs| xrefs.as_slice())
+    }
+
+    /// The direct addresses the instruction at `offset` reads or writes
+    pub fn memory_references_from(&self, offset: usize) -> &[(u16, MemoryAccess)] {
+        self.memory_from.get(&offset).map_or(&[], |xrefs| xrefs.as_slice())
+    }
+}
+
+/// Resolves the offset a relative branch lands on, far and indirect branches are not resolved
+fn branch_target(instruction: &Instruction) -> Option<(usize, BranchKind)> {
+    let (displacement, kind) = match instruction.mnemonic {
+        Mnemonic::JO { label }
+        | Mnemonic::JNO { label }
+        | Mnemonic::JB { label }
+        | Mnemonic::JNB { label }
+        | Mnemonic::JE { label }
+        | Mnemonic::JNE { label }
+        | Mnemonic::JBE { label }
+        | Mnemonic::JNBE { label }
+        | Mnemonic::JS { label }
+        | Mnemonic::JNS { label }
+        | Mnemonic::JP { label }
+        | Mnemonic::JNP { label }
+        | Mnemonic::JL { label }
+        | Mnemonic::JNL { label }
+        | Mnemonic::JLE { label }
+        | Mnemonic::JNLE { label }
+        | Mnemonic::JCXZ { label } => (label as i8 as i16, BranchKind::Jump),
+        Mnemonic::JMP { label } => (label, BranchKind::Jump),
+        Mnemonic::LOOP { short_label }
+        | Mnemonic::LOOPE { short_label }
+        | Mnemonic::LOOPNE { short_label } => (short_label as i8 as i16, BranchKind::Loop),
+        Mnemonic::CALL {
+            near_proc: Some(near_proc),
+            ..
+        } => (near_proc, BranchKind::Call),
+        _ => return None,
+    };
+    let next = (instruction.offset + instruction.length) as u16;
+    Some((next.wrapping_add(displacement as u16) as usize, kind))
+}
+
+fn memory_operands(mnemonic: &Mnemonic) -> Vec<(RegisterMemory, MemoryAccess)> {
+    let def_use = mnemonic.def_use();
+    let reads = def_use.uses.into_iter().map(|location| (location, MemoryAccess::Read));
+    let writes = def_use.defs.into_iter().map(|location| (location, MemoryAccess::Write));
+    reads
+        .chain(writes)
+        .filter_map(|(location, access)| match location {
+            Location::Memory(operand) => Some((operand, access)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BranchKind, MemoryAccess, XrefTable};
+    use crate::opcodes::disassemble;
+
+    #[test]
+    fn test_branch_xrefs() {
+        // mov cx, 3; top: dec cx; jnz top; loop top; call top
+        let binary = [
+            0xb9, 0x03, 0x00, 0x49, 0x75, 0xfd, 0xe2, 0xfb, 0xe8, 0xf8, 0xff,
+        ];
+        let listing = disassemble(&binary).unwrap();
+        let xrefs = XrefTable::new(&listing);
+        assert_eq!(
+            xrefs.branches_to(3),
+            &[
+                (4, BranchKind::Jump),
+                (6, BranchKind::Loop),
+                (8, BranchKind::Call)
+            ]
+        );
+        assert!(xrefs.branches_to(0).is_empty());
+    }
+
+    #[test]
+    fn test_memory_xrefs() {
+        // mov [1000], ax; add cx, [1000]; mov dx, [bp+1000]
+        let binary = [
+            0x89, 0x06, 0xe8, 0x03, 0x03, 0x0e, 0xe8, 0x03, 0x8b, 0x96, 0xe8, 0x03,
+        ];
+        let listing = disassemble(&binary).unwrap();
+        let xrefs = XrefTable::new(&listing);
+        assert_eq!(
+            xrefs.memory_references(1000),
+            &[(0, MemoryAccess::Write), (4, MemoryAccess::Read)]
+        );
+        assert_eq!(xrefs.memory_references_from(4), &[(1000, MemoryAccess::Read)]);
+        assert!(xrefs.memory_references_from(8).is_empty());
+    }
+
+    #[test]
+    fn test_register_relative_memory_xrefs() {
+        // mov [4], ax; add cx, [bx+4]
+        let binary = [0x89, 0x06, 0x04, 0x00, 0x03, 0x4f, 0x04];
+        let listing = disassemble(&binary).unwrap();
+        let xrefs = XrefTable::new(&listing);
+        assert_eq!(xrefs.memory_references(4), &[(0, MemoryAccess::Write)]);
+        assert!(xrefs.memory_references_from(4).is_empty());
+    }
+}